@@ -16,11 +16,14 @@
                 version = cargo_manifest.package.version;
                 src = ./.;
                 cargoLock.lockFile = ./Cargo.lock;
+                # The backend tests run a private dbus-daemon
+                nativeCheckInputs = with pkgs; [ dbus ];
             };
             devShells.default = pkgs.mkShell {
                 packages = with pkgs; [
                     cargo
                     clippy
+                    dbus
                     rustfmt
                 ];
             };
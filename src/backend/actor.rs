@@ -0,0 +1,439 @@
+use super::containers::get_containers;
+use super::messages::{
+    Command, Container, ContainerId, LogSource, NamedCommand, NamedUpdate, NewContainer,
+    OperationOutcome, UnitOperation, Update,
+};
+use super::systemd::Systemd;
+use super::{CommandReceiver, Sender, report_config_errors, spawn_monitors, utils};
+use anyhow::{Context, Error, Result, anyhow};
+use inotify::{EventStream, Inotify, WatchMask};
+use std::collections::{HashMap, VecDeque};
+use std::future;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::task::{self, AbortHandle, JoinSet};
+use tokio_stream::StreamExt;
+
+/// Name used for messages from the container config watcher
+const WATCHER_NAME: &str = "container watcher";
+
+/// Backend task running commands from the TUI, owning the tasks of every
+/// container and watching for containers being created or destroyed
+pub struct Actor {
+    channel: Sender,
+    systemd: Systemd,
+    /// Directory holding the container configs
+    config_dir: PathBuf,
+    /// Path of the nixos-container program
+    nixos_container: PathBuf,
+    containers: HashMap<ContainerId, ContainerTasks>,
+    /// Running unit operations, each giving the container it is on
+    operations: JoinSet<ContainerId>,
+    /// Running nixos-container commands, which may add or remove configs,
+    /// each giving the container it is on and what it does
+    tools: JoinSet<(ContainerId, ToolKind)>,
+}
+
+/// What a running nixos-container command does
+enum ToolKind {
+    Create,
+    Destroy,
+    Rebuild,
+}
+
+/// Tasks and unit operations of a single container
+struct ContainerTasks {
+    /// The container as last loaded from its config
+    container: Container,
+    monitors: Vec<AbortHandle>,
+    /// Task following the guest journal, if it is a log source
+    guest_log: Option<AbortHandle>,
+    /// Unit operation running on the container
+    running: Option<UnitOperation>,
+    /// Unit operations waiting for the running one to finish
+    queued: VecDeque<UnitOperation>,
+    /// Whether the container is being destroyed, so takes no other commands
+    destroying: bool,
+    /// Whether the container is being rebuilt
+    rebuilding: bool,
+}
+
+impl Drop for ContainerTasks {
+    fn drop(&mut self) {
+        for task in self.monitors.iter().chain(&self.guest_log) {
+            task.abort();
+        }
+    }
+}
+
+impl Actor {
+    /// Start monitoring the initial containers
+    pub fn new(
+        channel: Sender,
+        systemd: Systemd,
+        config_dir: PathBuf,
+        nixos_container: PathBuf,
+        containers: &[Container],
+    ) -> Self {
+        let mut actor = Self {
+            channel,
+            systemd,
+            config_dir,
+            nixos_container,
+            containers: HashMap::new(),
+            operations: JoinSet::new(),
+            tools: JoinSet::new(),
+        };
+        for container in containers {
+            actor.add_container(container);
+        }
+        actor
+    }
+
+    /// Run commands until the TUI stops sending them
+    pub async fn run(mut self, mut commands: CommandReceiver) {
+        let mut configs = match watch_configs(&self.config_dir) {
+            Ok(configs) => {
+                self.log("Watching for container changes");
+                Some(configs)
+            }
+            Err(error) => {
+                self.error(error);
+                None
+            }
+        };
+        loop {
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(command) => self.command(command),
+                    None => break,
+                },
+                Some(finished) = self.operations.join_next() => {
+                    let container = finished.expect("Unit operations should not panic");
+                    self.operation_finished(&container);
+                }
+                // Pick up config changes even if they are not being watched
+                Some(finished) = self.tools.join_next() => {
+                    let (container, kind) =
+                        finished.expect("nixos-container commands should not panic");
+                    self.rescan();
+                    // Allow retrying if destroying the container failed
+                    if let Some(tasks) = self.containers.get_mut(&container) {
+                        match kind {
+                            ToolKind::Create => (),
+                            ToolKind::Destroy => tasks.destroying = false,
+                            ToolKind::Rebuild => tasks.rebuilding = false,
+                        }
+                    }
+                }
+                event = next_config_event(&mut configs) => match event
+                    .context("Failed to read container config directory event")
+                {
+                    Ok(()) => self.rescan(),
+                    Err(error) => self.error(error),
+                },
+            }
+        }
+    }
+
+    fn command(&mut self, command: NamedCommand) {
+        let container = command.container;
+        let (channel, systemd) = (self.channel.clone(), self.systemd.clone());
+        match command.inner {
+            Command::Create(options) => self.create(container, options),
+            Command::Unit(operation) => {
+                let Some(tasks) = self.accepting(&container) else {
+                    return;
+                };
+                let outcome =
+                    if tasks.running == Some(operation) || tasks.queued.contains(&operation) {
+                        OperationOutcome::Rejected
+                    } else if tasks.running.is_some() {
+                        tasks.queued.push_back(operation);
+                        OperationOutcome::Queued
+                    } else {
+                        self.start_operation(&container, operation);
+                        return;
+                    };
+                self.send(container, Update::Operation(operation, outcome));
+            }
+            Command::SetLogSource(source) => {
+                let Some(tasks) = self.accepting(&container) else {
+                    return;
+                };
+                if let Some(task) = tasks.guest_log.take() {
+                    task.abort();
+                }
+                if let LogSource::Guest(unit) = source {
+                    tasks.guest_log = Some(
+                        task::spawn(super::follow_guest_log(container, channel, unit))
+                            .abort_handle(),
+                    );
+                }
+            }
+            Command::LoadLogHistory {
+                source,
+                range,
+                before,
+            } => {
+                if self.accepting(&container).is_some() {
+                    task::spawn(super::load_log_history(
+                        container, channel, source, range, before,
+                    ));
+                }
+            }
+            Command::ExportLog {
+                path,
+                contents,
+                count,
+            } => {
+                if self.accepting(&container).is_some() {
+                    task::spawn(super::export_log(container, channel, path, contents, count));
+                }
+            }
+            Command::Destroy => {
+                let Some(tasks) = self.accepting(&container) else {
+                    return;
+                };
+                if tasks.rebuilding {
+                    self.send(
+                        container,
+                        Update::Error(anyhow!("Container is being rebuilt")),
+                    );
+                    return;
+                }
+                tasks.destroying = true;
+                // Queued operations could start the container again between
+                // stopping and destroying it
+                let rejected = tasks.queued.drain(..).collect::<Vec<_>>();
+                for operation in rejected {
+                    self.send(
+                        container.clone(),
+                        Update::Operation(operation, OperationOutcome::Rejected),
+                    );
+                }
+                let program = self.nixos_container.clone();
+                self.tools.spawn(async move {
+                    super::destroy_container(container.clone(), channel, systemd, program).await;
+                    (container, ToolKind::Destroy)
+                });
+            }
+            Command::Rebuild(source) => {
+                let Some(tasks) = self.accepting(&container) else {
+                    return;
+                };
+                if tasks.rebuilding {
+                    self.send(
+                        container,
+                        Update::Error(anyhow!("Container is already being rebuilt")),
+                    );
+                    return;
+                }
+                tasks.rebuilding = true;
+                let program = self.nixos_container.clone();
+                self.tools.spawn(async move {
+                    super::rebuild_container(container.clone(), channel, program, source).await;
+                    (container, ToolKind::Rebuild)
+                });
+            }
+        }
+    }
+
+    /// Get the tasks of a container if it can take commands, reporting if it
+    /// is being destroyed
+    fn accepting(&mut self, container: &ContainerId) -> Option<&mut ContainerTasks> {
+        // The container may have been destroyed after the command was sent
+        let tasks = self.containers.get_mut(container)?;
+        if tasks.destroying {
+            self.channel
+                .send(NamedUpdate {
+                    container: container.clone(),
+                    inner: Update::Error(anyhow!("Container is being destroyed")),
+                })
+                .expect("Channel should always be open");
+            return None;
+        }
+        Some(tasks)
+    }
+
+    /// Create a container, unless one of the same name exists
+    fn create(&mut self, container: ContainerId, options: NewContainer) {
+        if self.containers.contains_key(&container) {
+            self.send(
+                container,
+                Update::Error(anyhow!("Failed to create container: it already exists")),
+            );
+            return;
+        }
+        let (channel, program) = (self.channel.clone(), self.nixos_container.clone());
+        self.tools.spawn(async move {
+            super::create_container(container.clone(), channel, program, options).await;
+            (container, ToolKind::Create)
+        });
+    }
+
+    fn start_operation(&mut self, container: &ContainerId, operation: UnitOperation) {
+        let tasks = self
+            .containers
+            .get_mut(container)
+            .expect("Container should be present");
+        tasks.running = Some(operation);
+        let (container, channel, systemd) = (
+            container.clone(),
+            self.channel.clone(),
+            self.systemd.clone(),
+        );
+        self.operations.spawn(async move {
+            let (c, s) = (container.clone(), channel);
+            match operation {
+                UnitOperation::Start => super::start_container(c, s, systemd).await,
+                UnitOperation::Stop => super::stop_container(c, s, systemd).await,
+                UnitOperation::Restart => super::restart_container(c, s, systemd).await,
+                UnitOperation::TryRestart => super::try_restart_container(c, s, systemd).await,
+                UnitOperation::ReloadOrRestart => {
+                    super::reload_or_restart_container(c, s, systemd).await
+                }
+            }
+            container
+        });
+    }
+
+    /// Report a finished unit operation and start the next queued one
+    fn operation_finished(&mut self, container: &ContainerId) {
+        // Ignore operations on destroyed containers
+        let Some(tasks) = self.containers.get_mut(container) else {
+            return;
+        };
+        let finished = tasks
+            .running
+            .take()
+            .expect("Finished operation should be running");
+        // Destroying the container rejected anything queued
+        let next = (!tasks.destroying)
+            .then(|| tasks.queued.pop_front())
+            .flatten();
+        self.send(
+            container.clone(),
+            Update::Operation(finished, OperationOutcome::Finished),
+        );
+        if let Some(next) = next {
+            self.start_operation(container, next);
+        }
+    }
+
+    /// Start or stop monitoring containers to match their config files
+    fn rescan(&mut self) {
+        let containers = match get_containers(&self.config_dir) {
+            Ok(containers) => containers,
+            Err(error) => {
+                self.error(error);
+                return;
+            }
+        };
+        // Stop monitoring destroyed containers
+        let removed = self
+            .containers
+            .keys()
+            .filter(|id| !containers.iter().any(|(container, _)| &container.id == *id))
+            .cloned()
+            .collect::<Vec<_>>();
+        for id in removed {
+            self.containers.remove(&id);
+            let systemd = self.systemd.clone();
+            let unit_name = utils::service_name(&id);
+            task::spawn(async move { systemd.forget_unit(&unit_name).await });
+            self.send(id, Update::ContainerRemoved);
+        }
+        // Start monitoring created containers, and report edited configs
+        for (container, errors) in containers {
+            if let Some(tasks) = self.containers.get_mut(&container.id) {
+                if tasks.container != container {
+                    tasks.container = container.clone();
+                    report_config_errors(&container.id, &self.channel, errors);
+                    self.send(
+                        container.id.clone(),
+                        Update::ContainerChanged(Box::new(container)),
+                    );
+                }
+                continue;
+            }
+            // Let the TUI add the container before its monitors report on it
+            self.send(
+                container.id.clone(),
+                Update::ContainerAdded(Box::new(container.clone())),
+            );
+            report_config_errors(&container.id, &self.channel, errors);
+            self.add_container(&container);
+        }
+    }
+
+    fn add_container(&mut self, container: &Container) {
+        self.containers.insert(
+            container.id.clone(),
+            ContainerTasks {
+                container: container.clone(),
+                monitors: spawn_monitors(container, &self.channel, &self.systemd),
+                guest_log: None,
+                running: None,
+                queued: VecDeque::new(),
+                destroying: false,
+                rebuilding: false,
+            },
+        );
+    }
+
+    fn log(&self, message: &str) {
+        self.send(
+            ContainerId::new(WATCHER_NAME),
+            Update::Log(message.to_string()),
+        );
+    }
+
+    fn error(&self, error: Error) {
+        self.send(
+            ContainerId::new(WATCHER_NAME),
+            Update::Error(error.context("Failed to watch for container changes")),
+        );
+    }
+
+    fn send(&self, container: ContainerId, update: Update) {
+        self.channel
+            .send(NamedUpdate {
+                container,
+                inner: update,
+            })
+            .expect("Channel should always be open");
+    }
+}
+
+/// Watch the container config directory for containers being created,
+/// edited or destroyed
+fn watch_configs(config_dir: &Path) -> Result<EventStream<[u8; 1024]>> {
+    let inotify = Inotify::init().context("Failed to initialize inotify")?;
+    inotify
+        .watches()
+        .add(
+            config_dir,
+            WatchMask::CREATE
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO,
+        )
+        .context("Failed to watch container config directory")?;
+    inotify
+        .into_event_stream([0; 1024])
+        .context("Failed to read container config directory events")
+}
+
+/// Wait for the next change to the container config directory, or forever if
+/// it is not being watched
+async fn next_config_event(configs: &mut Option<EventStream<[u8; 1024]>>) -> io::Result<()> {
+    let Some(configs) = configs else {
+        return future::pending().await;
+    };
+    match configs.next().await {
+        Some(event) => event.map(|_| ()),
+        None => future::pending().await,
+    }
+}
@@ -0,0 +1,188 @@
+use super::messages::{Address, Container, ContainerConfig, ContainerId, PortForward};
+use super::utils;
+use anyhow::{Context, Error, Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the config file of each container
+pub const CONTAINER_CONFIG_DIR: &str = "/etc/nixos-containers";
+
+/// Directory holding the state root of each container
+pub const CONTAINER_STATE_DIR: &str = "/var/lib/nixos-containers";
+
+/// Get the list of containers configured in a directory, sorted by name,
+/// along with any invalid settings found in their configs
+pub fn get_containers(config_dir: &Path) -> Result<Vec<(Container, Vec<Error>)>> {
+    let mut containers = fs::read_dir(config_dir)
+        .context("Failed to list container configs")?
+        .map(|entry| load_container(&entry.context("Failed to get container config")?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    containers.sort_by(|(a, _), (b, _)| a.id.cmp(&b.id));
+    Ok(containers)
+}
+
+/// Load a container from its config file
+fn load_container(config_path: &Path) -> Result<(Container, Vec<Error>)> {
+    let name = config_path
+        .file_stem()
+        .ok_or(anyhow!("Container config name is not of expected form"))?
+        .to_str()
+        .ok_or_else(|| {
+            anyhow!(
+                "Container config name contains invalid UTF-8: {}",
+                config_path.to_string_lossy()
+            )
+        })?;
+    let id = ContainerId::new(name);
+    let contents = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config for container {name}"))?;
+    let (config, errors) = parse_config(&contents);
+    let container = Container {
+        unit_name: utils::service_name(&id),
+        config_path: config_path.to_path_buf(),
+        state_root: Path::new(CONTAINER_STATE_DIR).join(name),
+        config,
+        id,
+    };
+    Ok((container, errors))
+}
+
+/// Parse a container config, collecting errors for invalid settings
+///
+/// Invalid settings are left at their defaults so the rest of the config is
+/// still usable
+pub fn parse_config(contents: &str) -> (ContainerConfig, Vec<Error>) {
+    let mut config = ContainerConfig::default();
+    let mut errors = Vec::new();
+    for (key, value) in parse_settings(contents) {
+        if let Err(error) = apply_setting(&mut config, &key, &value) {
+            errors.push(error.context(format!("Invalid setting {key}")));
+        }
+    }
+    let has_network_settings = config.host_bridge.is_some()
+        || config.host_address.is_some()
+        || config.local_address.is_some()
+        || config.host_address6.is_some()
+        || config.local_address6.is_some();
+    if has_network_settings && !config.private_network {
+        errors.push(anyhow!(
+            "Network addresses or bridge are set but PRIVATE_NETWORK is disabled"
+        ));
+    }
+    (config, errors)
+}
+
+/// Apply a single config setting
+fn apply_setting(config: &mut ContainerConfig, key: &str, value: &str) -> Result<()> {
+    let value = value.trim();
+    match key {
+        "SYSTEM_PATH" => config.system_path = non_empty(value).map(PathBuf::from),
+        "PRIVATE_NETWORK" => config.private_network = parse_flag(value)?,
+        "AUTO_START" => config.auto_start = parse_flag(value)?,
+        "HOST_BRIDGE" => config.host_bridge = non_empty(value).map(str::to_string),
+        "HOST_ADDRESS" => config.host_address = parse_address(value, false)?,
+        "LOCAL_ADDRESS" => config.local_address = parse_address(value, false)?,
+        "HOST_ADDRESS6" => config.host_address6 = parse_address(value, true)?,
+        "LOCAL_ADDRESS6" => config.local_address6 = parse_address(value, true)?,
+        "HOST_PORT" => {
+            config.host_ports = value
+                .split([',', ' '])
+                .filter(|port| !port.is_empty())
+                .map(parse_port_forward)
+                .collect::<Result<_>>()?
+        }
+        "INTERFACES" => config.interfaces = split_list(value),
+        "MACVLANS" => config.macvlans = split_list(value),
+        "EXTRA_NSPAWN_FLAGS" => config.extra_nspawn_flags = split_list(value),
+        "NETWORK_NAMESPACE_PATH" => {
+            config.network_namespace_path = non_empty(value).map(PathBuf::from)
+        }
+        _ => {
+            config.other.insert(key.to_string(), value.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Parse a `0`/`1` flag, treating an empty value as unset
+fn parse_flag(value: &str) -> Result<bool> {
+    match value {
+        "1" => Ok(true),
+        "0" | "" => Ok(false),
+        _ => Err(anyhow!("Expected 0 or 1, got {value}")),
+    }
+}
+
+/// Parse an IP address with an optional prefix length
+fn parse_address(value: &str, ipv6: bool) -> Result<Option<Address>> {
+    let Some(value) = non_empty(value) else {
+        return Ok(None);
+    };
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (value, None),
+    };
+    let ip = ip
+        .parse::<IpAddr>()
+        .with_context(|| format!("Invalid IP address {ip}"))?;
+    if ip.is_ipv6() != ipv6 {
+        let expected = if ipv6 { "IPv6" } else { "IPv4" };
+        return Err(anyhow!("Expected an {expected} address, got {ip}"));
+    }
+    let max_prefix = if ipv6 { 128 } else { 32 };
+    let prefix = prefix
+        .map(|prefix| match prefix.parse::<u8>() {
+            Ok(prefix) if prefix <= max_prefix => Ok(prefix),
+            _ => Err(anyhow!("Invalid prefix length {prefix}")),
+        })
+        .transpose()?;
+    Ok(Some(Address { ip, prefix }))
+}
+
+/// Parse a `protocol:host_port:container_port` port forward
+fn parse_port_forward(value: &str) -> Result<PortForward> {
+    let parts = value.split(':').collect::<Vec<_>>();
+    let (protocol, host_port, container_port) = match parts[..] {
+        [protocol, host_port, container_port] => (protocol, host_port, container_port),
+        [protocol, host_port] => (protocol, host_port, host_port),
+        _ => return Err(anyhow!("Port forward {value} is not of expected form")),
+    };
+    let parse_port = |port: &str| {
+        port.parse::<u16>()
+            .with_context(|| format!("Invalid port {port}"))
+    };
+    Ok(PortForward {
+        protocol: protocol.to_string(),
+        host_port: parse_port(host_port)?,
+        container_port: parse_port(container_port)?,
+    })
+}
+
+/// Get a value if it is not empty
+fn non_empty(value: &str) -> Option<&str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// Split a whitespace-separated list
+fn split_list(value: &str) -> Vec<String> {
+    value.split_whitespace().map(str::to_string).collect()
+}
+
+/// Parse the environment-style `KEY=value` lines of a container config
+fn parse_settings(contents: &str) -> BTreeMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_string(), value.to_string())
+        })
+        .collect()
+}
@@ -0,0 +1,172 @@
+use super::messages::{JournalEntry, LogRange, Priority};
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
+
+/// Reader for structured journal entries, using `journalctl --output json`
+///
+/// Which entries are read is chosen by journalctl selection arguments, such
+/// as `--unit` or `--directory`.
+pub struct JournalReader {
+    /// The journalctl process, killed when the reader is dropped
+    child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+}
+
+impl JournalReader {
+    /// Follow a journal, starting from its most recent entries or after the
+    /// entry at a cursor if given
+    pub fn follow(selection: &[String], after: Option<&str>) -> Result<Self> {
+        let mut command = Command::new("journalctl");
+        command
+            .args(["--output", "json", "--follow"])
+            .args(selection);
+        if let Some(cursor) = after {
+            command.args(["--after-cursor", cursor]);
+        }
+        let mut child = command
+            .kill_on_drop(true)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn journalctl")?;
+        let lines =
+            BufReader::new(child.stdout.take().expect("Child stdio should be present")).lines();
+        Ok(Self { child, lines })
+    }
+
+    /// Read up to `count` of the newest entries of a journal within a range,
+    /// before the entry at a cursor if given
+    ///
+    /// Entries are returned from oldest to newest.
+    pub async fn read_history(
+        selection: &[String],
+        range: &LogRange,
+        before: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<JournalEntry>> {
+        let mut command = Command::new("journalctl");
+        command
+            .args(["--output", "json", "--reverse"])
+            .args(["--lines", &count.to_string()])
+            .args(selection);
+        if let Some(since) = &range.since {
+            command.args(["--since", since]);
+        }
+        if let Some(until) = &range.until {
+            command.args(["--until", until]);
+        }
+        if let Some(boot) = &range.boot {
+            command.args(["--boot", boot]);
+        }
+        // Reading in reverse after a cursor reads the entries before it
+        if let Some(cursor) = before {
+            command.args(["--after-cursor", cursor]);
+        }
+        let output = command.output().await.context("Failed to run journalctl")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "journalctl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let mut entries = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(parse_entry)
+            .collect::<Result<Vec<_>>>()?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Read the next entry, or `None` once journalctl exits successfully
+    pub async fn next_entry(&mut self) -> Result<Option<JournalEntry>> {
+        let Some(line) = self
+            .lines
+            .next_line()
+            .await
+            .context("Failed to read journal entry")?
+        else {
+            let status = self
+                .child
+                .wait()
+                .await
+                .context("Failed to wait for journalctl")?;
+            if status.success() {
+                return Ok(None);
+            }
+            let mut error = String::new();
+            if let Some(stderr) = &mut self.child.stderr {
+                stderr
+                    .read_to_string(&mut error)
+                    .await
+                    .context("Failed to read journalctl error")?;
+            }
+            return Err(anyhow!("journalctl failed: {}", error.trim()));
+        };
+        parse_entry(&line).map(Some)
+    }
+}
+
+/// Parse a journal entry from a line of journalctl JSON output
+///
+/// Optional fields that fail to parse are left out rather than losing the
+/// entry.
+pub fn parse_entry(line: &str) -> Result<JournalEntry> {
+    let Value::Object(object) = serde_json::from_str(line).context("Invalid journal entry")? else {
+        return Err(anyhow!("Journal entry is not an object"));
+    };
+    let fields = object
+        .into_iter()
+        .filter_map(|(name, value)| field_value(value).map(|value| (name, value)))
+        .collect::<BTreeMap<_, _>>();
+    let timestamp = fields
+        .get("__REALTIME_TIMESTAMP")
+        .ok_or(anyhow!("Journal entry has no timestamp"))?
+        .parse()
+        .context("Invalid journal entry timestamp")?;
+    let cursor = fields
+        .get("__CURSOR")
+        .ok_or(anyhow!("Journal entry has no cursor"))?
+        .clone();
+    let priority = fields
+        .get("PRIORITY")
+        .and_then(|priority| Priority::from_journal(priority).ok());
+    let pid = fields.get("_PID").and_then(|pid| pid.parse().ok());
+    let identifier = fields
+        .get("SYSLOG_IDENTIFIER")
+        .or_else(|| fields.get("_COMM"))
+        .cloned();
+    Ok(JournalEntry {
+        timestamp,
+        cursor,
+        priority,
+        pid,
+        identifier,
+        message: fields.get("MESSAGE").cloned().unwrap_or_default(),
+        fields,
+    })
+}
+
+/// Get the text of a journal field
+///
+/// Fields that are not valid UTF-8 are encoded as arrays of bytes, and fields
+/// set more than once as arrays of values, of which the first is kept
+fn field_value(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value),
+        Value::Array(values) if values.iter().all(Value::is_u64) => {
+            let bytes = values
+                .iter()
+                .filter_map(Value::as_u64)
+                .map(|byte| byte as u8)
+                .collect::<Vec<_>>();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        Value::Array(values) => values.into_iter().find_map(field_value),
+        value => Some(value.to_string()),
+    }
+}
@@ -1,14 +1,227 @@
 use anyhow::{Error, Result, anyhow};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 
-/// An update from the backend associated with a container name
+/// An update from the backend associated with a container
 #[derive(Debug)]
 pub struct NamedUpdate {
-    /// The name of the associated container
-    pub container_name: &'static str,
+    /// The associated container
+    pub container: ContainerId,
     /// The update from that container
     pub inner: Update,
 }
 
+/// A command to the backend concerning a container
+#[derive(Debug)]
+pub struct NamedCommand {
+    /// The container the command is for
+    pub container: ContainerId,
+    /// The command itself
+    pub inner: Command,
+}
+
+/// Commands the backend runs on behalf of the TUI
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Queue a job on the container service, after any already running
+    Unit(UnitOperation),
+    /// Follow the container's logs from a source, besides the host journal
+    /// which is always followed
+    SetLogSource(LogSource),
+    /// Read a page of the container's past logs
+    LoadLogHistory {
+        source: LogSource,
+        range: LogRange,
+        /// Cursor to read back from, or `None` for the newest entries
+        before: Option<String>,
+    },
+    /// Write exported log entries of the container to a file
+    ExportLog {
+        path: PathBuf,
+        contents: String,
+        /// Number of entries exported
+        count: usize,
+    },
+    /// Create the container with `nixos-container create`
+    Create(NewContainer),
+    /// Stop the container and destroy it with `nixos-container destroy`
+    Destroy,
+    /// Rebuild the container with `nixos-container update`, from a new
+    /// configuration if given
+    Rebuild(Option<ContainerSource>),
+}
+
+/// Settings for creating a container
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NewContainer {
+    /// Whether the container gets its own network stack, using the addresses
+    /// and bridge below
+    pub private_network: bool,
+    pub host_address: Option<String>,
+    pub local_address: Option<String>,
+    pub bridge: Option<String>,
+    /// Where the container's configuration comes from, or `None` for an
+    /// empty configuration
+    pub source: Option<ContainerSource>,
+}
+
+/// Source of a new container's NixOS configuration
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContainerSource {
+    /// A flake reference, like `github:owner/repo#name`
+    Flake(String),
+    /// A snippet of NixOS configuration
+    Config(String),
+    /// A file holding a NixOS configuration
+    ConfigFile(PathBuf),
+}
+
+/// Operations on a container service, only one of which runs at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitOperation {
+    Start,
+    Stop,
+    Restart,
+    TryRestart,
+    ReloadOrRestart,
+}
+
+impl fmt::Display for UnitOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::TryRestart => "try-restart",
+            Self::ReloadOrRestart => "reload-or-restart",
+        })
+    }
+}
+
+/// What became of a unit operation sent to the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationOutcome {
+    /// Waiting for another operation on the container to finish
+    Queued,
+    /// Dropped, as the same operation is already running or queued
+    Rejected,
+    /// Done running, with any failure reported as an error
+    Finished,
+}
+
+/// Cheap, clonable identifier for a container, holding its name
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContainerId(Arc<str>);
+
+impl ContainerId {
+    pub fn new(name: &str) -> Self {
+        Self(name.into())
+    }
+
+    /// Get the name of the container
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for ContainerId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for ContainerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A container known to the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    /// Identifier of the container
+    pub id: ContainerId,
+    /// Name of the systemd service running the container
+    pub unit_name: String,
+    /// Path to the container's config file
+    pub config_path: PathBuf,
+    /// Directory on the host holding the container's root filesystem
+    pub state_root: PathBuf,
+    /// Settings from the container's config file
+    pub config: ContainerConfig,
+}
+
+/// Settings from a container's config file in `/etc/nixos-containers`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerConfig {
+    /// Path of the container's system profile (`SYSTEM_PATH`)
+    pub system_path: Option<PathBuf>,
+    /// Whether the container has its own network stack (`PRIVATE_NETWORK`)
+    pub private_network: bool,
+    /// Whether the container starts at boot (`AUTO_START`)
+    pub auto_start: bool,
+    /// Bridge the host side of the veth pair is added to (`HOST_BRIDGE`)
+    pub host_bridge: Option<String>,
+    /// IPv4 address of the host side of the veth pair (`HOST_ADDRESS`)
+    pub host_address: Option<Address>,
+    /// IPv4 address of the container side of the veth pair (`LOCAL_ADDRESS`)
+    pub local_address: Option<Address>,
+    /// IPv6 address of the host side of the veth pair (`HOST_ADDRESS6`)
+    pub host_address6: Option<Address>,
+    /// IPv6 address of the container side of the veth pair (`LOCAL_ADDRESS6`)
+    pub local_address6: Option<Address>,
+    /// Ports forwarded from the host into the container (`HOST_PORT`)
+    pub host_ports: Vec<PortForward>,
+    /// Host interfaces moved into the container (`INTERFACES`)
+    pub interfaces: Vec<String>,
+    /// Host interfaces given a macvlan in the container (`MACVLANS`)
+    pub macvlans: Vec<String>,
+    /// Extra flags passed to systemd-nspawn (`EXTRA_NSPAWN_FLAGS`)
+    pub extra_nspawn_flags: Vec<String>,
+    /// Network namespace the container joins (`NETWORK_NAMESPACE_PATH`)
+    pub network_namespace_path: Option<PathBuf>,
+    /// Settings not otherwise recognized
+    pub other: BTreeMap<String, String>,
+}
+
+/// An IP address with an optional prefix length
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub ip: IpAddr,
+    pub prefix: Option<u8>,
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix {
+            Some(prefix) => write!(f, "{}/{prefix}", self.ip),
+            None => write!(f, "{}", self.ip),
+        }
+    }
+}
+
+/// A port forwarded from the host into a container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortForward {
+    pub protocol: String,
+    pub host_port: u16,
+    pub container_port: u16,
+}
+
+impl fmt::Display for PortForward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} -> {}",
+            self.protocol, self.host_port, self.container_port
+        )
+    }
+}
+
 /// An update message from the backend
 #[derive(Debug)]
 pub enum Update {
@@ -18,8 +231,204 @@ pub enum Update {
     Error(Error),
     /// State of the container status
     State(ContainerState),
-    /// Log message from the container
-    ContainerLog(String),
+    /// Journal entry from the container service or guest
+    ContainerLog(LogSource, Box<JournalEntry>),
+    /// Result of a job issued on the container service
+    JobFinished(JobResult),
+    /// The container was created while the backend was running
+    ContainerAdded(Box<Container>),
+    /// The container was destroyed while the backend was running
+    ContainerRemoved,
+    /// The config file of the container was edited
+    ContainerChanged(Box<Container>),
+    /// Current properties of the container service
+    UnitProperties(Box<UnitProperties>),
+    /// Sampled resource usage of the container service
+    Resources(Box<ResourceUsage>),
+    /// Recent resource usage samples of the container service
+    ResourceHistory(Box<ResourceHistory>),
+    /// Page of past journal entries from the container service
+    LogHistory(Box<LogPage>),
+    /// Whether the backend is connected to systemd
+    Connected(bool),
+    /// Progress of a unit operation on the container service
+    Operation(UnitOperation, OperationOutcome),
+    /// A line of output from rebuilding the container
+    BuildOutput(String),
+    /// Rebuilding the container succeeded, so it can be restarted
+    BuildSucceeded,
+    /// Rebuilding the container failed
+    BuildFailed(Error),
+}
+
+/// Properties of a container service, as reported by systemd
+#[derive(Debug, Clone)]
+pub struct UnitProperties {
+    pub description: String,
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+    /// When the unit last entered the active state, in microseconds since the
+    /// Unix epoch, or zero if it never has
+    pub active_enter_timestamp: u64,
+    /// ID of the current invocation of the unit, as a hex string
+    pub invocation_id: String,
+    pub unit_file_state: String,
+    pub fragment_path: String,
+    pub need_daemon_reload: bool,
+    /// Name and message of the error encountered while loading the unit
+    pub load_error: Option<(String, String)>,
+    pub requires: Vec<String>,
+    pub wants: Vec<String>,
+}
+
+/// Resource usage of a container service
+///
+/// Values are `None` when systemd does not account for them, for example
+/// because the container is not running
+#[derive(Debug, Clone, Default)]
+pub struct ResourceUsage {
+    /// Memory used, in bytes
+    pub memory: Option<u64>,
+    /// Total CPU time used, in nanoseconds
+    pub cpu_time: Option<u64>,
+    /// CPU usage since the previous sample, as a fraction of one CPU
+    pub cpu_rate: Option<f64>,
+    /// Number of tasks
+    pub tasks: Option<u64>,
+    /// Total bytes read from block devices
+    pub io_read: Option<u64>,
+    /// Total bytes written to block devices
+    pub io_write: Option<u64>,
+    /// Total bytes received over IP
+    pub ip_ingress: Option<u64>,
+    /// Total bytes sent over IP
+    pub ip_egress: Option<u64>,
+}
+
+/// Recent resource usage samples of a container service, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ResourceHistory {
+    /// Memory used, in bytes
+    pub memory: VecDeque<Option<u64>>,
+    /// CPU usage, as a fraction of one CPU
+    pub cpu: VecDeque<Option<f64>>,
+}
+
+impl ResourceHistory {
+    /// Add a sample, dropping the oldest ones to keep at most `limit`
+    pub fn push(&mut self, usage: &ResourceUsage, limit: usize) {
+        self.memory.push_back(usage.memory);
+        self.cpu.push_back(usage.cpu_rate);
+        while self.memory.len() > limit {
+            self.memory.pop_front();
+        }
+        while self.cpu.len() > limit {
+            self.cpu.pop_front();
+        }
+    }
+}
+
+/// An entry from the systemd journal
+#[derive(Debug, Clone)]
+pub struct JournalEntry {
+    /// When the entry was logged, in microseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Position of the entry in the journal
+    pub cursor: String,
+    pub priority: Option<Priority>,
+    /// ID of the logging process
+    pub pid: Option<u32>,
+    /// Syslog identifier or command name of the logging process
+    pub identifier: Option<String>,
+    pub message: String,
+    /// All fields of the entry, including those above
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Journal a container's logs are read from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum LogSource {
+    /// The container service on the host
+    #[default]
+    Host,
+    /// The guest's own journal, of all units or only the given one
+    Guest(Option<String>),
+}
+
+/// Range of past journal entries to browse
+///
+/// The bounds are passed to journalctl as-is, so accept anything its
+/// `--since`, `--until` and `--boot` options do.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogRange {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Boot ID or offset, such as `-1` for the previous boot
+    pub boot: Option<String>,
+}
+
+/// Page of past journal entries within a [`LogRange`]
+#[derive(Debug, Clone)]
+pub struct LogPage {
+    pub source: LogSource,
+    pub range: LogRange,
+    /// Cursor the page was read back from, or `None` for the newest page
+    pub before: Option<String>,
+    /// Entries from oldest to newest
+    pub entries: Vec<JournalEntry>,
+    /// Whether there are no entries in the range before this page
+    pub complete: bool,
+}
+
+/// Syslog priority of a journal entry, from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Emergency,
+    Alert,
+    Critical,
+    Error,
+    Warning,
+    Notice,
+    Info,
+    Debug,
+}
+
+impl Priority {
+    /// All priorities, from most to least severe
+    pub const ALL: [Self; 8] = [
+        Self::Emergency,
+        Self::Alert,
+        Self::Critical,
+        Self::Error,
+        Self::Warning,
+        Self::Notice,
+        Self::Info,
+        Self::Debug,
+    ];
+
+    /// Parse a priority from a journal `PRIORITY` field
+    pub fn from_journal(priority: &str) -> Result<Self> {
+        priority
+            .parse::<usize>()
+            .ok()
+            .and_then(|priority| Self::ALL.get(priority).copied())
+            .ok_or_else(|| anyhow!("Unrecognized journal priority {priority}"))
+    }
+
+    /// Get the short syslog name of the priority
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Emergency => "emerg",
+            Self::Alert => "alert",
+            Self::Critical => "crit",
+            Self::Error => "err",
+            Self::Warning => "warning",
+            Self::Notice => "notice",
+            Self::Info => "info",
+            Self::Debug => "debug",
+        }
+    }
 }
 
 /// The state of a container service
@@ -51,3 +460,29 @@ impl ContainerState {
         }
     }
 }
+
+/// The result of a finished systemd job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobResult {
+    Done,
+    Canceled,
+    Timeout,
+    Failed,
+    Dependency,
+    Skipped,
+}
+
+impl JobResult {
+    /// Parse a job result from a systemd message
+    pub fn from_systemd(result: &str) -> Result<Self> {
+        match result {
+            "done" => Ok(Self::Done),
+            "canceled" => Ok(Self::Canceled),
+            "timeout" => Ok(Self::Timeout),
+            "failed" => Ok(Self::Failed),
+            "dependency" => Ok(Self::Dependency),
+            "skipped" => Ok(Self::Skipped),
+            _ => Err(anyhow!("Unrecognized job result {result}")),
+        }
+    }
+}
@@ -1,15 +1,30 @@
-use anyhow::{Context, Result, anyhow};
-use messages::{ContainerState, NamedUpdate, Update};
-use proxies::{ManagerProxy, UnitProxy};
+use actor::Actor;
+use anyhow::{Context, Error, Result, anyhow};
+pub use containers::CONTAINER_CONFIG_DIR;
+use containers::get_containers;
+use journal::JournalReader;
+use messages::{
+    Container, ContainerId, ContainerSource, ContainerState, JobResult, LogPage, LogRange,
+    LogSource, NamedCommand, NamedUpdate, NewContainer, ResourceHistory, ResourceUsage,
+    UnitProperties, Update,
+};
+use nixos_container::NixosContainer;
+use proxies::{JobRemovedStream, ManagerProxy};
+use std::collections::HashMap;
 use std::fs;
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use systemd::Systemd;
 use tokio::sync::mpsc;
-use tokio::task;
+use tokio::task::{self, AbortHandle};
+use tokio::time::{self, MissedTickBehavior};
 use tokio_stream::StreamExt;
 use utils::log;
-use zbus::Connection;
+use zbus::Address;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{ObjectPath, OwnedValue};
 
 /// Data structures for communicating with the backend
 pub mod messages;
@@ -19,47 +34,67 @@ pub mod messages;
 #[allow(clippy::type_complexity)]
 mod proxies;
 
+/// Discovery of containers from their config files
+mod containers;
+
+/// Structured reading of the systemd journal
+mod journal;
+
+/// Shared connection to systemd
+mod systemd;
+
+/// Backend task running commands from the TUI
+mod actor;
+
+/// Running the nixos-container tool
+mod nixos_container;
+
+/// Tests of the backend against a mock systemd
+#[cfg(test)]
+mod tests;
+
 /// Backend helper macros
 mod utils;
 
-/// Set up backend communication with systemd over dbus
-pub async fn start_backend() -> Result<(Receiver, Vec<&'static str>, Sender)> {
-    // Connect to systemd over dbus
-    let connection = Connection::system()
-        .await
-        .context("Could not connect to DBus")?;
-    // Get list of containers to monitor
-    let containers = get_containers()?;
+/// Set up backend communication with systemd on the dbus bus at an address,
+/// for the containers configured in a directory, managing them with the
+/// given nixos-container program
+pub async fn start_backend(
+    address: Address,
+    config_dir: PathBuf,
+    nixos_container: PathBuf,
+) -> Result<(Receiver, Vec<Container>, CommandSender)> {
     // Create channel for recieving updates from monitors
     let (send, recv) = mpsc::unbounded_channel();
-    // Spawn tasks for monitoring each container
-    for container in &containers {
-        task::spawn(monitor_container_status(
-            container,
-            send.clone(),
-            connection.clone(),
-        ));
-        task::spawn(monitor_container_log(container, send.clone()));
+    // Talk to systemd over dbus
+    let systemd = Systemd::connect(address, send.clone()).await?;
+    // Get list of containers to monitor
+    let (containers, config_errors): (Vec<_>, Vec<_>) =
+        get_containers(&config_dir)?.into_iter().unzip();
+    for (container, errors) in containers.iter().zip(config_errors) {
+        report_config_errors(&container.id, &send, errors);
     }
-    // Return backend message reciever
-    Ok((recv, containers, send))
+    // Spawn the actor monitoring the containers and running commands on them
+    let (commands, command_recv) = mpsc::unbounded_channel();
+    task::spawn(
+        Actor::new(send, systemd, config_dir, nixos_container, &containers).run(command_recv),
+    );
+    // Return backend message reciever and command sender
+    Ok((recv, containers, commands))
 }
 
 utils::report_async! {
     /// Start a container
-    pub start_container[c, s]() {
-        let service_name = utils::service_name(c);
-        let connection = Connection::system()
-            .await
-            .context("Could not connect to DBus")?;
-        let manager = ManagerProxy::new(&connection)
-            .await
-            .context("Failed to connect to systemd manager")?;
+    start_container[c, s](systemd: Systemd) {
+        let service_name = utils::service_name(&c);
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
         log!(c, s, "Issuing start command");
-        manager.start_unit(&service_name, "replace")
+        let job = manager.start_unit(&service_name, "replace")
             .await
             .context("Failed to start container service")?;
         log!(c, s, "Starting");
+        track_job(&c, &s, &mut jobs, &job).await?;
         Ok(())
     }
     "Failed to start container"
@@ -67,91 +102,342 @@ utils::report_async! {
 
 utils::report_async! {
     /// Stop a container
-    pub stop_container[c, s]() {
-        let service_name = utils::service_name(c);
-        let connection = Connection::system()
-            .await
-            .context("Could not connect to DBus")?;
-        let manager = ManagerProxy::new(&connection)
-            .await
-            .context("Failed to connect to systemd manager")?;
+    stop_container[c, s](systemd: Systemd) {
+        let service_name = utils::service_name(&c);
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
         log!(c, s, "Issuing stop command");
-        manager.stop_unit(&service_name, "replace")
+        let job = manager.stop_unit(&service_name, "replace")
             .await
             .context("Failed to stop container service")?;
         log!(c, s, "Stopping");
+        track_job(&c, &s, &mut jobs, &job).await?;
         Ok(())
     }
     "Failed to stop container"
 }
 
+utils::report_async! {
+    /// Restart a container
+    restart_container[c, s](systemd: Systemd) {
+        let service_name = utils::service_name(&c);
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
+        log!(c, s, "Issuing restart command");
+        let job = manager.restart_unit(&service_name, "replace")
+            .await
+            .context("Failed to restart container service")?;
+        log!(c, s, "Queued restart job {job}");
+        track_job(&c, &s, &mut jobs, &job).await?;
+        Ok(())
+    }
+    "Failed to restart container"
+}
+
+utils::report_async! {
+    /// Restart a container if it is already running
+    try_restart_container[c, s](systemd: Systemd) {
+        let service_name = utils::service_name(&c);
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
+        log!(c, s, "Issuing try-restart command");
+        let job = manager.try_restart_unit(&service_name, "replace")
+            .await
+            .context("Failed to try-restart container service")?;
+        log!(c, s, "Queued try-restart job {job}");
+        track_job(&c, &s, &mut jobs, &job).await?;
+        Ok(())
+    }
+    "Failed to try-restart container"
+}
+
+utils::report_async! {
+    /// Reload a container, or restart it if reloading is unsupported
+    reload_or_restart_container[c, s](systemd: Systemd) {
+        let service_name = utils::service_name(&c);
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
+        log!(c, s, "Issuing reload-or-restart command");
+        let job = manager.reload_or_restart_unit(&service_name, "replace")
+            .await
+            .context("Failed to reload or restart container service")?;
+        log!(c, s, "Queued reload-or-restart job {job}");
+        track_job(&c, &s, &mut jobs, &job).await?;
+        Ok(())
+    }
+    "Failed to reload or restart container"
+}
+
+/// Listen for completed systemd jobs
+///
+/// This must be done before queueing a job to avoid missing its completion
+async fn subscribe_jobs(manager: &ManagerProxy<'_>) -> Result<JobRemovedStream> {
+    manager
+        .receive_job_removed()
+        .await
+        .context("Failed to listen for job completion")
+}
+
+/// Wait for a queued job to be removed and report its result
+async fn track_job(
+    container: &ContainerId,
+    channel: &Sender,
+    jobs: &mut JobRemovedStream,
+    job: &ObjectPath<'_>,
+) -> Result<JobResult> {
+    while let Some(removed) = jobs.next().await {
+        let args = removed.args().context("Failed to parse job removal")?;
+        if args.job() == job {
+            let result = JobResult::from_systemd(args.result())?;
+            channel
+                .send(NamedUpdate {
+                    container: container.clone(),
+                    inner: Update::JobFinished(result),
+                })
+                .expect("Channel should always be open");
+            return Ok(result);
+        }
+    }
+    Err(anyhow!("Job removal stream ended before job finished"))
+}
+
+/// How often container states are re-read to recover from missed signals
+const STATE_RESYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long to wait before restarting a monitor that stopped
+const MONITOR_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// How often the resource usage of containers is sampled
+const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How many resource usage samples are kept for each container
+const RESOURCE_HISTORY_LENGTH: usize = 60;
+
 /// Type of the reciever for messages from the backend
 pub type Receiver = mpsc::UnboundedReceiver<NamedUpdate>;
 
 /// Type of senders for transmitting messages out of the backend
 pub type Sender = mpsc::UnboundedSender<NamedUpdate>;
 
+/// Type of senders for issuing commands to the backend
+pub type CommandSender = mpsc::UnboundedSender<NamedCommand>;
+
+/// Type of the reciever for commands to the backend
+type CommandReceiver = mpsc::UnboundedReceiver<NamedCommand>;
+
 utils::report_async! {
     /// Monitor the status of a container
-    monitor_container_status[c, s](connection: Connection) {
-        let service_name = utils::service_name(c);
-        // Connect to systemd over dbus
-        log!(c, s, "Connecting to systemd");
-        let manager = ManagerProxy::new(&connection)
-            .await
-            .context("Failed to connect to systemd manager")?;
-        let path = manager
-            .load_unit(&service_name)
+    monitor_container_status[c, s](service_name: String, systemd: Systemd) {
+        log!(c, s, "Loading unit");
+        let unit = systemd.unit(&service_name).await?;
+        let mut state_stream = unit.receive_active_state_changed().await;
+        // Report the state the unit is already in
+        let active_state = unit
+            .active_state()
             .await
-            .context("Failed to get unit path")?;
-        let unit = UnitProxy::new(&connection, path)
+            .context("Failed to get initial state")?;
+        let sub_state = unit
+            .sub_state()
             .await
-            .context("Failed to connect to unit object")?;
-        let mut state_stream = unit.receive_active_state_changed().await;
+            .context("Failed to get initial sub-state")?;
+        log!(c, s, "Initial state {active_state} ({sub_state})");
+        send_state(&c, &s, &active_state)?;
         log!(c, s, "Monitoring");
-        // Listen for state changes
-        while let Some(state) = state_stream.next().await {
-            let state = ContainerState::from_systemd(
-                &state.get().await.context("Failed to get updated state")?,
-            )?;
+        // Listen for state changes, periodically re-reading the state in
+        // case a change signal was missed
+        let mut resync = time::interval(STATE_RESYNC_INTERVAL);
+        resync.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        resync.tick().await;
+        loop {
+            let state = tokio::select! {
+                state = state_stream.next() => match state {
+                    Some(state) => state.get().await.context("Failed to get updated state")?,
+                    None => break,
+                },
+                _ = resync.tick() => unit
+                    .active_state()
+                    .await
+                    .context("Failed to resync state")?,
+            };
+            send_state(&c, &s, &state)?;
+        }
+        Ok(())
+    }
+    "Failed to set up status monitoring"
+}
+
+/// Parse a systemd active state and send it as a state update
+fn send_state(container: &ContainerId, channel: &Sender, state: &str) -> Result<()> {
+    let state = ContainerState::from_systemd(state)?;
+    channel
+        .send(NamedUpdate {
+            container: container.clone(),
+            inner: Update::State(state),
+        })
+        .expect("Channel should always be open");
+    Ok(())
+}
+
+utils::report_async! {
+    /// Monitor the properties of a container's service
+    monitor_unit_properties[c, s](service_name: String, systemd: Systemd) {
+        let properties = unit_properties(&systemd, &service_name).await?;
+        let mut changes = properties
+            .receive_properties_changed()
+            .await
+            .context("Failed to listen for property changes")?;
+        loop {
+            let properties = read_unit_properties(&properties).await?;
             s.send(NamedUpdate {
-                container_name: c,
-                inner: Update::State(state),
+                container: c.clone(),
+                inner: Update::UnitProperties(Box::new(properties)),
             })
             .expect("Channel should always be open");
+            if changes.next().await.is_none() {
+                break;
+            }
         }
         Ok(())
     }
-    "Failed to set up status monitoring"
+    "Failed to set up unit property monitoring"
+}
+
+/// Connect to the properties of a container's service object
+async fn unit_properties(
+    systemd: &Systemd,
+    service_name: &str,
+) -> Result<PropertiesProxy<'static>> {
+    let connection = systemd.connection().await?;
+    let path = systemd.unit(service_name).await?.inner().path().to_owned();
+    PropertiesProxy::builder(&connection)
+        .destination("org.freedesktop.systemd1")
+        .context("Invalid systemd service name")?
+        .path(path)
+        .context("Invalid unit path")?
+        .build()
+        .await
+        .context("Failed to connect to unit properties")
+}
+
+/// Read the properties of a container's service, all at once so they are
+/// consistent with each other
+async fn read_unit_properties(properties: &PropertiesProxy<'_>) -> Result<UnitProperties> {
+    let interface = InterfaceName::from_static_str("org.freedesktop.systemd1.Unit")
+        .expect("Unit interface name should be valid");
+    let mut values = properties
+        .get_all(interface)
+        .await
+        .context("Failed to get unit properties")?;
+    let load_error: (String, String) = property(&mut values, "LoadError")?;
+    Ok(UnitProperties {
+        description: property(&mut values, "Description")?,
+        load_state: property(&mut values, "LoadState")?,
+        active_state: property(&mut values, "ActiveState")?,
+        sub_state: property(&mut values, "SubState")?,
+        active_enter_timestamp: property(&mut values, "ActiveEnterTimestamp")?,
+        invocation_id: property::<Vec<u8>>(&mut values, "InvocationID")?
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+        unit_file_state: property(&mut values, "UnitFileState")?,
+        fragment_path: property(&mut values, "FragmentPath")?,
+        need_daemon_reload: property(&mut values, "NeedDaemonReload")?,
+        load_error: (!load_error.0.is_empty()).then_some(load_error),
+        requires: property(&mut values, "Requires")?,
+        wants: property(&mut values, "Wants")?,
+    })
+}
+
+/// Take a property out of those read from dbus, as its expected type
+fn property<T>(values: &mut HashMap<String, OwnedValue>, name: &str) -> Result<T>
+where
+    T: TryFrom<OwnedValue>,
+    Error: From<T::Error>,
+{
+    let value = values
+        .remove(name)
+        .ok_or_else(|| anyhow!("Missing {name} property"))?;
+    T::try_from(value)
+        .map_err(Error::from)
+        .with_context(|| format!("Invalid {name} property"))
 }
 
 utils::report_async! {
-    /// Monitor logs from a container
-    monitor_container_log[c, s]() {
+    /// Periodically sample the resource usage of a container's service
+    monitor_container_resources[c, s](service_name: String, systemd: Systemd) {
+        let properties = unit_properties(&systemd, &service_name).await?;
+        let mut sample = time::interval(RESOURCE_SAMPLE_INTERVAL);
+        sample.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut previous: Option<(Instant, u64)> = None;
+        let mut history = ResourceHistory::default();
+        loop {
+            sample.tick().await;
+            let now = Instant::now();
+            let mut usage = read_resource_usage(&properties).await?;
+            // Work out the CPU rate from the change since the last sample
+            usage.cpu_rate = previous
+                .zip(usage.cpu_time)
+                .filter(|((_, previous_time), time)| time >= previous_time)
+                .map(|((previous_instant, previous_time), time)| {
+                    (time - previous_time) as f64
+                        / now.duration_since(previous_instant).as_nanos() as f64
+                });
+            previous = usage.cpu_time.map(|time| (now, time));
+            history.push(&usage, RESOURCE_HISTORY_LENGTH);
+            s.send(NamedUpdate {
+                container: c.clone(),
+                inner: Update::Resources(Box::new(usage)),
+            })
+            .expect("Channel should always be open");
+            s.send(NamedUpdate {
+                container: c.clone(),
+                inner: Update::ResourceHistory(Box::new(history.clone())),
+            })
+            .expect("Channel should always be open");
+        }
+    }
+    "Failed to set up resource monitoring"
+}
+
+/// Read the resource accounting properties of a container's service, all at
+/// once so they are sampled together
+async fn read_resource_usage(properties: &PropertiesProxy<'_>) -> Result<ResourceUsage> {
+    let interface = InterfaceName::from_static_str("org.freedesktop.systemd1.Service")
+        .expect("Service interface name should be valid");
+    let mut values = properties
+        .get_all(interface)
+        .await
+        .context("Failed to get resource usage")?;
+    // Systemd reports unavailable values as the maximum integer
+    let mut available =
+        |name| property::<u64>(&mut values, name).map(|value| (value != u64::MAX).then_some(value));
+    Ok(ResourceUsage {
+        memory: available("MemoryCurrent")?,
+        cpu_time: available("CPUUsageNSec")?,
+        cpu_rate: None,
+        tasks: available("TasksCurrent")?,
+        io_read: available("IOReadBytes")?,
+        io_write: available("IOWriteBytes")?,
+        ip_ingress: available("IPIngressBytes")?,
+        ip_egress: available("IPEgressBytes")?,
+    })
+}
+
+utils::report_async! {
+    /// Monitor logs from a container, continuing after the entry at the last
+    /// cursor seen if it is being restarted
+    monitor_container_log[c, s](service_name: String, last_cursor: Arc<Mutex<Option<String>>>) {
         log!(c, s, "Requesting logs");
-        let mut child = Command::new("journalctl")
-            .args([
-                "--no-hostname",
-                "--follow",
-                "--unit",
-                &utils::service_name(c),
-            ])
-            .kill_on_drop(true)
-            .stdout(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn journalctl")?;
-        let mut reader =
-            BufReader::new(child.stdout.take().expect("Child stdio should be present")).lines();
-        tokio::spawn(async move { child.wait().await });
+        let selection = journal_selection(&c, &service_name, &LogSource::Host);
+        let after = last_cursor.lock().expect("Cursor lock should not be poisoned").clone();
+        let mut journal = JournalReader::follow(&selection, after.as_deref())?;
         log!(c, s, "Reading logs");
-        while let Some(line) = reader
-            .next_line()
-            .await
-            .context("Failed to read log line")?
-        {
+        while let Some(entry) = journal.next_entry().await? {
+            *last_cursor.lock().expect("Cursor lock should not be poisoned") =
+                Some(entry.cursor.clone());
             s.send(NamedUpdate {
-                container_name: c,
-                inner: Update::ContainerLog(line),
+                container: c.clone(),
+                inner: Update::ContainerLog(LogSource::Host, Box::new(entry)),
             })
             .expect("Channel should always be open");
         }
@@ -160,28 +446,241 @@ utils::report_async! {
     "Failed to set up log monitoring"
 }
 
-/// Get the list of container names
-///
-/// This leaks the container name strings to make cheap, copyable identifiers
-fn get_containers() -> Result<Vec<&'static str>> {
-    let mut configs = fs::read_dir("/etc/nixos-containers")
-        .context("Failed to list container configs")?
-        .map(|entry| {
-            entry
-                .context("Failed to get container config")?
-                .file_name()
-                .into_string()
-                .map_err(|failed| {
-                    anyhow!(
-                        "Container config name contains invalid UTF-8: {}",
-                        failed.to_string_lossy()
-                    )
-                })?
-                .rsplit_once('.')
-                .map(|(name, _)| name.to_string().leak() as &'static str)
-                .ok_or(anyhow!("Container config name is not of expected form"))
+utils::report_async! {
+    /// Follow the guest's own journal, of all units or only the given one
+    follow_guest_log[c, s](unit: Option<String>) {
+        let source = LogSource::Guest(unit);
+        let selection = journal_selection(&c, &utils::service_name(&c), &source);
+        let mut journal = JournalReader::follow(&selection, None)?;
+        while let Some(entry) = journal.next_entry().await? {
+            s.send(NamedUpdate {
+                container: c.clone(),
+                inner: Update::ContainerLog(source.clone(), Box::new(entry)),
+            })
+            .expect("Channel should always be open");
+        }
+        Ok(())
+    }
+    "Failed to follow guest logs"
+}
+
+utils::report_async! {
+    /// Write exported log entries of a container to a file
+    export_log[c, s](path: PathBuf, contents: String, count: usize) {
+        let display = path.display().to_string();
+        task::spawn_blocking(move || fs::write(path, contents))
+            .await
+            .context("Log export task failed")?
+            .with_context(|| format!("Failed to write {display}"))?;
+        log!(c, s, "Exported {count} log entries to {display}");
+        Ok(())
+    }
+    "Failed to export logs"
+}
+
+utils::report_async! {
+    /// Create a container with nixos-container, logging its output
+    create_container[c, s](program: PathBuf, options: NewContainer) {
+        log!(c, s, "Creating container");
+        let mut tool = NixosContainer::spawn(&program, &nixos_container::create_args(&c, &options))?;
+        while let Some(line) = tool.next_line().await? {
+            s.send(NamedUpdate {
+                container: c.clone(),
+                inner: Update::Log(line),
+            })
+            .expect("Channel should always be open");
+        }
+        log!(c, s, "Created container");
+        Ok(())
+    }
+    "Failed to create container"
+}
+
+utils::report_async! {
+    /// Stop a container, then destroy it with nixos-container, logging its
+    /// output
+    destroy_container[c, s](systemd: Systemd, program: PathBuf) {
+        let manager = systemd.manager().await?;
+        let mut jobs = subscribe_jobs(&manager).await?;
+        log!(c, s, "Stopping container to destroy it");
+        let job = manager.stop_unit(&utils::service_name(&c), "replace")
+            .await
+            .context("Failed to stop container service")?;
+        if track_job(&c, &s, &mut jobs, &job).await? != JobResult::Done {
+            return Err(anyhow!("Container did not stop"));
+        }
+        log!(c, s, "Destroying container");
+        let mut tool = NixosContainer::spawn(&program, &nixos_container::destroy_args(&c))?;
+        while let Some(line) = tool.next_line().await? {
+            s.send(NamedUpdate {
+                container: c.clone(),
+                inner: Update::Log(line),
+            })
+            .expect("Channel should always be open");
+        }
+        log!(c, s, "Destroyed container");
+        Ok(())
+    }
+    "Failed to destroy container"
+}
+
+/// Rebuild a container with nixos-container, from a new configuration if
+/// given, streaming its output and reporting whether it succeeded
+async fn rebuild_container(
+    container: ContainerId,
+    channel: Sender,
+    program: PathBuf,
+    source: Option<ContainerSource>,
+) {
+    let send = |update| {
+        channel
+            .send(NamedUpdate {
+                container: container.clone(),
+                inner: update,
+            })
+            .expect("Channel should always be open")
+    };
+    let rebuild = async {
+        let args = nixos_container::update_args(&container, source.as_ref());
+        let mut tool = NixosContainer::spawn(&program, &args)?;
+        while let Some(line) = tool.next_line().await? {
+            send(Update::BuildOutput(line));
+        }
+        Ok::<_, Error>(())
+    };
+    match rebuild.await {
+        Ok(()) => send(Update::BuildSucceeded),
+        Err(error) => send(Update::BuildFailed(
+            error.context("Failed to rebuild container"),
+        )),
+    }
+}
+
+/// Get the journalctl arguments selecting a container's logs from a source
+fn journal_selection(container: &ContainerId, unit_name: &str, source: &LogSource) -> Vec<String> {
+    match source {
+        LogSource::Host => vec!["--unit".to_string(), unit_name.to_string()],
+        LogSource::Guest(unit) => {
+            let directory = utils::guest_journal_dir(container);
+            let mut selection = vec![
+                "--directory".to_string(),
+                directory.to_string_lossy().into_owned(),
+            ];
+            if let Some(unit) = unit {
+                selection.extend(["--unit".to_string(), unit.clone()]);
+            }
+            selection
+        }
+    }
+}
+
+/// How many past journal entries are read at a time
+const LOG_HISTORY_PAGE: usize = 200;
+
+utils::report_async! {
+    /// Read a page of a container's past logs from a source within a range,
+    /// before the entry at a cursor if given
+    load_log_history[c, s](source: LogSource, range: LogRange, before: Option<String>) {
+        let selection = journal_selection(&c, &utils::service_name(&c), &source);
+        let read = JournalReader::read_history(
+            &selection,
+            &range,
+            before.as_deref(),
+            LOG_HISTORY_PAGE,
+        )
+        .await;
+        // Send an empty page on failure, so the TUI can try loading it again
+        let (entries, complete, result) = match read {
+            Ok(entries) => {
+                let complete = entries.len() < LOG_HISTORY_PAGE;
+                (entries, complete, Ok(()))
+            }
+            Err(error) => (Vec::new(), false, Err(error)),
+        };
+        s.send(NamedUpdate {
+            container: c.clone(),
+            inner: Update::LogHistory(Box::new(LogPage {
+                source,
+                range,
+                before,
+                entries,
+                complete,
+            })),
         })
-        .collect::<Result<Vec<_>>>()?;
-    configs.sort();
-    Ok(configs)
+        .expect("Channel should always be open");
+        result
+    }
+    "Failed to load log history"
+}
+
+/// Report problems found in a container's config
+fn report_config_errors(container: &ContainerId, channel: &Sender, errors: Vec<Error>) {
+    for error in errors {
+        channel
+            .send(NamedUpdate {
+                container: container.clone(),
+                inner: Update::Error(error.context("Invalid container config")),
+            })
+            .expect("Channel should always be open");
+    }
+}
+
+/// Spawn the tasks monitoring a single container
+fn spawn_monitors(container: &Container, channel: &Sender, systemd: &Systemd) -> Vec<AbortHandle> {
+    let (id, unit_name) = (&container.id, &container.unit_name);
+    vec![
+        task::spawn(supervise(systemd.clone(), {
+            let (id, channel, unit_name) = (id.clone(), channel.clone(), unit_name.clone());
+            move |systemd| {
+                monitor_container_status(id.clone(), channel.clone(), unit_name.clone(), systemd)
+            }
+        }))
+        .abort_handle(),
+        task::spawn(restart({
+            let (id, channel, unit_name) = (id.clone(), channel.clone(), unit_name.clone());
+            let last_cursor = Arc::default();
+            move || {
+                monitor_container_log(
+                    id.clone(),
+                    channel.clone(),
+                    unit_name.clone(),
+                    Arc::clone(&last_cursor),
+                )
+            }
+        }))
+        .abort_handle(),
+        task::spawn(supervise(systemd.clone(), {
+            let (id, channel, unit_name) = (id.clone(), channel.clone(), unit_name.clone());
+            move |systemd| {
+                monitor_unit_properties(id.clone(), channel.clone(), unit_name.clone(), systemd)
+            }
+        }))
+        .abort_handle(),
+        task::spawn(supervise(systemd.clone(), {
+            let (id, channel, unit_name) = (id.clone(), channel.clone(), unit_name.clone());
+            move |systemd| {
+                monitor_container_resources(id.clone(), channel.clone(), unit_name.clone(), systemd)
+            }
+        }))
+        .abort_handle(),
+    ]
+}
+
+/// Keep a monitor using the systemd connection running, restarting it once
+/// the connection works again whenever it stops
+async fn supervise<F: Future<Output = ()>>(systemd: Systemd, monitor: impl Fn(Systemd) -> F) {
+    loop {
+        monitor(systemd.clone()).await;
+        time::sleep(MONITOR_RESTART_DELAY).await;
+        systemd.reconnect().await;
+    }
+}
+
+/// Keep a monitor not using the systemd connection running, restarting it
+/// after a delay whenever it stops
+async fn restart<F: Future<Output = ()>>(monitor: impl Fn() -> F) {
+    loop {
+        monitor().await;
+        time::sleep(MONITOR_RESTART_DELAY).await;
+    }
 }
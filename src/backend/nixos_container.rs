@@ -0,0 +1,124 @@
+use super::messages::{ContainerId, ContainerSource, NewContainer};
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+
+/// A running `nixos-container` command, with its output read line by line
+///
+/// Output on stdout and stderr is interleaved in the order it is read.
+pub struct NixosContainer {
+    /// The nixos-container process, killed when dropped
+    child: Child,
+    stdout: Option<Lines<BufReader<ChildStdout>>>,
+    stderr: Option<Lines<BufReader<ChildStderr>>>,
+    /// Subcommand being run, for error messages
+    subcommand: String,
+}
+
+impl NixosContainer {
+    /// Run a `nixos-container` program with some arguments, starting with a
+    /// subcommand
+    pub fn spawn(program: &Path, args: &[String]) -> Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .kill_on_drop(true)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to spawn nixos-container")?;
+        let stdout = child.stdout.take().expect("Child stdio should be present");
+        let stderr = child.stderr.take().expect("Child stdio should be present");
+        Ok(Self {
+            child,
+            stdout: Some(BufReader::new(stdout).lines()),
+            stderr: Some(BufReader::new(stderr).lines()),
+            subcommand: args.first().cloned().unwrap_or_default(),
+        })
+    }
+
+    /// Read the next line of output, or `None` once nixos-container exits
+    /// successfully
+    pub async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let line = match (&mut self.stdout, &mut self.stderr) {
+                (Some(stdout), Some(stderr)) => tokio::select! {
+                    line = stdout.next_line() => (line, true),
+                    line = stderr.next_line() => (line, false),
+                },
+                (Some(stdout), None) => (stdout.next_line().await, true),
+                (None, Some(stderr)) => (stderr.next_line().await, false),
+                (None, None) => break,
+            };
+            match line {
+                (Ok(Some(line)), _) => return Ok(Some(line)),
+                (Ok(None), true) => self.stdout = None,
+                (Ok(None), false) => self.stderr = None,
+                (Err(error), _) => {
+                    return Err(error).context("Failed to read nixos-container output");
+                }
+            }
+        }
+        let status = self
+            .child
+            .wait()
+            .await
+            .context("Failed to wait for nixos-container")?;
+        if status.success() {
+            Ok(None)
+        } else {
+            Err(anyhow!(
+                "nixos-container {} failed with {status}",
+                self.subcommand
+            ))
+        }
+    }
+}
+
+/// Get the nixos-container arguments destroying a container
+pub fn destroy_args(container: &ContainerId) -> Vec<String> {
+    vec!["destroy".to_string(), container.name().to_string()]
+}
+
+/// Get the nixos-container arguments creating a container
+pub fn create_args(container: &ContainerId, options: &NewContainer) -> Vec<String> {
+    let mut args = vec!["create".to_string(), container.name().to_string()];
+    // nixos-container gives containers a private network whenever one of
+    // these is set
+    if options.private_network {
+        if let Some(address) = &options.host_address {
+            args.extend(["--host-address".to_string(), address.clone()]);
+        }
+        if let Some(address) = &options.local_address {
+            args.extend(["--local-address".to_string(), address.clone()]);
+        }
+        if let Some(bridge) = &options.bridge {
+            args.extend(["--bridge".to_string(), bridge.clone()]);
+        }
+    }
+    args.extend(source_args(options.source.as_ref()));
+    args
+}
+
+/// Get the nixos-container arguments rebuilding a container, from a new
+/// configuration if given
+pub fn update_args(container: &ContainerId, source: Option<&ContainerSource>) -> Vec<String> {
+    let mut args = vec!["update".to_string(), container.name().to_string()];
+    args.extend(source_args(source));
+    args
+}
+
+/// Get the nixos-container arguments choosing a container's configuration
+fn source_args(source: Option<&ContainerSource>) -> Vec<String> {
+    match source {
+        Some(ContainerSource::Flake(flake)) => vec!["--flake".to_string(), flake.clone()],
+        Some(ContainerSource::Config(config)) => vec!["--config".to_string(), config.clone()],
+        Some(ContainerSource::ConfigFile(path)) => vec![
+            "--config-file".to_string(),
+            path.to_string_lossy().into_owned(),
+        ],
+        None => Vec::new(),
+    }
+}
@@ -1,4 +1,4 @@
-pub use manager::ManagerProxy;
+pub use manager::{JobRemovedStream, ManagerProxy};
 pub use unit::UnitProxy;
 
 /// Main systemd manager interface
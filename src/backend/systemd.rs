@@ -0,0 +1,167 @@
+use super::Sender;
+use super::messages::{ContainerId, NamedUpdate, Update};
+use super::proxies::{ManagerProxy, UnitProxy};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time;
+use zbus::fdo::DBusProxy;
+use zbus::{Address, Connection, connection};
+
+/// Name used for messages about the connection to systemd
+const CONNECTION_NAME: &str = "systemd connection";
+
+/// How long to wait between attempts to reconnect to the bus
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connection to systemd shared by all backend tasks
+///
+/// Once the bus drops, operations fail until [`Systemd::reconnect`] manages
+/// to connect again, and the TUI is told whether it is connected.
+#[derive(Clone)]
+pub struct Systemd {
+    bus: Arc<Mutex<Option<Bus>>>,
+    /// Held by the task reconnecting, so others wait for it instead of
+    /// reconnecting themselves
+    reconnecting: Arc<Mutex<()>>,
+    /// Address of the bus systemd is on, connected to again if it drops
+    address: Address,
+    channel: Sender,
+}
+
+/// A working connection to systemd
+struct Bus {
+    connection: Connection,
+    manager: ManagerProxy<'static>,
+    /// Proxies of the units loaded so far, by unit name
+    units: HashMap<String, UnitProxy<'static>>,
+}
+
+impl Systemd {
+    /// Talk to systemd on the bus at an address, reporting later connection
+    /// changes to a channel
+    pub async fn connect(address: Address, channel: Sender) -> Result<Self> {
+        Ok(Self {
+            bus: Arc::new(Mutex::new(Some(Bus::connect(&address).await?))),
+            reconnecting: Arc::default(),
+            address,
+            channel,
+        })
+    }
+
+    /// Get the connection to the bus
+    pub async fn connection(&self) -> Result<Connection> {
+        let bus = self.bus.lock().await;
+        Ok(Self::connected(&bus)?.connection.clone())
+    }
+
+    /// Get the systemd manager
+    pub async fn manager(&self) -> Result<ManagerProxy<'static>> {
+        let bus = self.bus.lock().await;
+        Ok(Self::connected(&bus)?.manager.clone())
+    }
+
+    /// Get a unit, loading it the first time it is used on the connection
+    pub async fn unit(&self, unit_name: &str) -> Result<UnitProxy<'static>> {
+        let mut bus = self.bus.lock().await;
+        let bus = Self::connected_mut(&mut bus)?;
+        if let Some(unit) = bus.units.get(unit_name) {
+            return Ok(unit.clone());
+        }
+        let path = bus
+            .manager
+            .load_unit(unit_name)
+            .await
+            .context("Failed to get unit path")?;
+        let unit = UnitProxy::new(&bus.connection, path)
+            .await
+            .context("Failed to connect to unit object")?;
+        bus.units.insert(unit_name.to_string(), unit.clone());
+        Ok(unit)
+    }
+
+    /// Drop the cached proxy of a unit that no longer exists
+    pub async fn forget_unit(&self, unit_name: &str) {
+        if let Some(bus) = &mut *self.bus.lock().await {
+            bus.units.remove(unit_name);
+        }
+    }
+
+    /// Wait until there is a working connection to systemd, reconnecting if
+    /// the bus dropped
+    ///
+    /// The bus is not locked while reconnecting, so other tasks fail fast
+    /// while disconnected.
+    pub async fn reconnect(&self) {
+        let _reconnecting = self.reconnecting.lock().await;
+        let connection = self.connection().await.ok();
+        if let Some(connection) = connection
+            && is_alive(&connection).await
+        {
+            return;
+        }
+        if self.bus.lock().await.take().is_some() {
+            self.report(false);
+        }
+        loop {
+            match Bus::connect(&self.address).await {
+                Ok(connected) => {
+                    *self.bus.lock().await = Some(connected);
+                    self.report(true);
+                    return;
+                }
+                Err(_) => time::sleep(RECONNECT_INTERVAL).await,
+            }
+        }
+    }
+
+    fn connected(bus: &Option<Bus>) -> Result<&Bus> {
+        bus.as_ref().ok_or(anyhow!("Disconnected from systemd"))
+    }
+
+    fn connected_mut(bus: &mut Option<Bus>) -> Result<&mut Bus> {
+        bus.as_mut().ok_or(anyhow!("Disconnected from systemd"))
+    }
+
+    fn report(&self, connected: bool) {
+        self.channel
+            .send(NamedUpdate {
+                container: ContainerId::new(CONNECTION_NAME),
+                inner: Update::Connected(connected),
+            })
+            .expect("Channel should always be open");
+    }
+}
+
+impl Bus {
+    async fn connect(address: &Address) -> Result<Self> {
+        let connection = connection::Builder::address(address.clone())
+            .context("Invalid bus address")?
+            .build()
+            .await
+            .context("Could not connect to DBus")?;
+        let manager = ManagerProxy::new(&connection)
+            .await
+            .context("Failed to connect to systemd manager")?;
+        // Have systemd send job signals for as long as the connection lasts
+        manager
+            .subscribe()
+            .await
+            .context("Failed to subscribe to systemd signals")?;
+        Ok(Self {
+            connection,
+            manager,
+            units: HashMap::new(),
+        })
+    }
+}
+
+/// Check if a bus connection still works
+async fn is_alive(connection: &Connection) -> bool {
+    match DBusProxy::new(connection).await {
+        Ok(dbus) => dbus.get_id().await.is_ok(),
+        Err(_) => false,
+    }
+}
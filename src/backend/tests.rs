@@ -0,0 +1,560 @@
+use super::containers::parse_config;
+use super::journal::parse_entry;
+use super::messages::{
+    Command, ContainerId, ContainerSource, ContainerState, JobResult, LogRange, LogSource,
+    NamedCommand, NewContainer, OperationOutcome, Priority, UnitOperation, Update,
+};
+use super::nixos_container::{create_args, update_args};
+use super::{CommandSender, Receiver, start_backend};
+use mock_systemd::{MEMORY_CURRENT, MockSystemd};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time;
+
+/// Fake systemd on a private bus
+mod mock_systemd;
+
+/// How long to wait for the backend to react before failing
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Start the backend against a mock systemd with the given containers
+async fn start(containers: &[(&str, &str)]) -> (MockSystemd, Receiver, CommandSender) {
+    let systemd = MockSystemd::start().await;
+    for (name, active_state) in containers {
+        systemd.add_container(name, active_state).await;
+    }
+    let (recv, _, commands) = start_backend(
+        systemd.address(),
+        systemd.config_dir(),
+        systemd.nixos_container(),
+    )
+    .await
+    .expect("Backend should start");
+    (systemd, recv, commands)
+}
+
+/// Wait for updates about a container matching each of the expected ones, in
+/// any order, skipping any others
+async fn expect_updates(recv: &mut Receiver, container: &str, expected: &[fn(&Update) -> bool]) {
+    let mut remaining = expected.to_vec();
+    let mut skipped = Vec::new();
+    let found = time::timeout(TIMEOUT, async {
+        while !remaining.is_empty() {
+            let message = recv.recv().await.expect("Backend should be running");
+            let matching = (message.container.name() == container)
+                .then(|| {
+                    remaining
+                        .iter()
+                        .position(|expected| expected(&message.inner))
+                })
+                .flatten();
+            match matching {
+                Some(index) => {
+                    remaining.remove(index);
+                }
+                None => skipped.push(message),
+            }
+        }
+    })
+    .await;
+    if found.is_err() {
+        panic!(
+            "Expected {} more updates for {container}, got {skipped:#?}",
+            remaining.len()
+        );
+    }
+}
+
+/// Wait for an update about a container, skipping any others
+async fn expect_update(recv: &mut Receiver, container: &str, expected: fn(&Update) -> bool) {
+    expect_updates(recv, container, &[expected]).await;
+}
+
+/// Wait until the pending jobs are the given kinds
+async fn expect_jobs(systemd: &MockSystemd, expected: &[&str]) {
+    time::timeout(TIMEOUT, async {
+        while systemd.pending_jobs().await != expected {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("Expected jobs {expected:?}"));
+}
+
+fn send(commands: &CommandSender, container: &str, command: Command) {
+    commands
+        .send(NamedCommand {
+            container: ContainerId::new(container),
+            inner: command,
+        })
+        .expect("Backend should be running");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_initial_states() {
+    let (_systemd, mut recv, _commands) = start(&[("alpha", "active"), ("beta", "inactive")]).await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::State(ContainerState::Up))
+    })
+    .await;
+    expect_update(&mut recv, "beta", |update| {
+        matches!(update, Update::State(ContainerState::Down))
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_unit_properties() {
+    let (_systemd, mut recv, _commands) = start(&[("alpha", "active")]).await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::UnitProperties(properties)
+                if properties.description == "Container 'alpha'"
+                    && properties.active_state == "active"
+                    && properties.load_error.is_none()
+        )
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_resource_usage() {
+    let (_systemd, mut recv, _commands) = start(&[("alpha", "active")]).await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Resources(usage)
+                if usage.memory == Some(MEMORY_CURRENT)
+                    && usage.cpu_time.is_none()
+                    && usage.tasks.is_none()
+                    && usage.ip_egress.is_none()
+        )
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn start_reports_job_result_and_new_state() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "inactive")]).await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::State(ContainerState::Down))
+    })
+    .await;
+    send(&commands, "alpha", Command::Unit(UnitOperation::Start));
+    expect_jobs(&systemd, &["start"]).await;
+    systemd.finish_job("done").await;
+    expect_updates(
+        &mut recv,
+        "alpha",
+        &[
+            |update| matches!(update, Update::JobFinished(JobResult::Done)),
+            |update| matches!(update, Update::State(ContainerState::Up)),
+            |update| {
+                matches!(
+                    update,
+                    Update::Operation(UnitOperation::Start, OperationOutcome::Finished)
+                )
+            },
+        ],
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_reports_failed_job() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "active")]).await;
+    send(&commands, "alpha", Command::Unit(UnitOperation::Stop));
+    expect_jobs(&systemd, &["stop"]).await;
+    systemd.finish_job("failed").await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::JobFinished(JobResult::Failed))
+    })
+    .await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Stop, OperationOutcome::Finished)
+        )
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn conflicting_operations_are_queued_or_rejected() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "inactive")]).await;
+    send(&commands, "alpha", Command::Unit(UnitOperation::Start));
+    send(&commands, "alpha", Command::Unit(UnitOperation::Start));
+    send(&commands, "alpha", Command::Unit(UnitOperation::Stop));
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Start, OperationOutcome::Rejected)
+        )
+    })
+    .await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Stop, OperationOutcome::Queued)
+        )
+    })
+    .await;
+    // Only the first operation runs until it finishes
+    expect_jobs(&systemd, &["start"]).await;
+    systemd.finish_job("done").await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Start, OperationOutcome::Finished)
+        )
+    })
+    .await;
+    expect_jobs(&systemd, &["stop"]).await;
+    systemd.finish_job("done").await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Stop, OperationOutcome::Finished)
+        )
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn detects_created_and_destroyed_containers() {
+    let (systemd, mut recv, _commands) = start(&[("alpha", "active")]).await;
+    expect_update(&mut recv, "container watcher", |update| {
+        matches!(update, Update::Log(_))
+    })
+    .await;
+    systemd.add_container("beta", "active").await;
+    // The TUI hears of the container before anything its monitors report
+    let first = time::timeout(TIMEOUT, async {
+        loop {
+            let message = recv.recv().await.expect("Backend should be running");
+            if message.container.name() == "beta" {
+                break message.inner;
+            }
+        }
+    })
+    .await
+    .expect("New container should be reported");
+    assert!(matches!(first, Update::ContainerAdded(_)));
+    expect_update(&mut recv, "beta", |update| {
+        matches!(update, Update::State(ContainerState::Up))
+    })
+    .await;
+    fs::remove_file(systemd.config_dir().join("beta.conf")).expect("Config should be removable");
+    expect_update(&mut recv, "beta", |update| {
+        matches!(update, Update::ContainerRemoved)
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_edited_configs() {
+    let (systemd, mut recv, _commands) = start(&[("alpha", "active")]).await;
+    expect_update(&mut recv, "container watcher", |update| {
+        matches!(update, Update::Log(_))
+    })
+    .await;
+    fs::write(
+        systemd.config_dir().join("alpha.conf"),
+        "AUTO_START=1\nPRIVATE_NETWORK=maybe\n",
+    )
+    .expect("Config should be writable");
+    expect_updates(
+        &mut recv,
+        "alpha",
+        &[
+            |update| matches!(update, Update::ContainerChanged(container) if container.config.auto_start),
+            |update| matches!(update, Update::Error(_)),
+        ],
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn destroy_aborts_if_container_does_not_stop() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "active")]).await;
+    send(&commands, "alpha", Command::Destroy);
+    expect_jobs(&systemd, &["stop"]).await;
+    // Other commands are refused until destroying is done
+    send(&commands, "alpha", Command::Unit(UnitOperation::Start));
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::Error(error) if error.to_string() == "Container is being destroyed")
+    })
+    .await;
+    systemd.finish_job("failed").await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::Error(error) if format!("{error:#}").ends_with("Container did not stop"))
+    })
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn destroy_stops_and_removes_container() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "active")]).await;
+    send(&commands, "alpha", Command::Destroy);
+    expect_jobs(&systemd, &["stop"]).await;
+    systemd.finish_job("done").await;
+    expect_updates(
+        &mut recv,
+        "alpha",
+        &[
+            |update| matches!(update, Update::Log(log) if log == "Destroyed alpha"),
+            |update| matches!(update, Update::ContainerRemoved),
+        ],
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn destroy_rejects_queued_operations() {
+    let (systemd, mut recv, commands) = start(&[("alpha", "inactive")]).await;
+    send(&commands, "alpha", Command::Unit(UnitOperation::Start));
+    send(&commands, "alpha", Command::Unit(UnitOperation::Restart));
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Restart, OperationOutcome::Queued)
+        )
+    })
+    .await;
+    expect_jobs(&systemd, &["start"]).await;
+    send(&commands, "alpha", Command::Destroy);
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Restart, OperationOutcome::Rejected)
+        )
+    })
+    .await;
+    expect_jobs(&systemd, &["start", "stop"]).await;
+    // Finishing the start does not run the restart before destroying
+    systemd.finish_job("done").await;
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(
+            update,
+            Update::Operation(UnitOperation::Start, OperationOutcome::Finished)
+        )
+    })
+    .await;
+    time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(systemd.pending_jobs().await, ["stop"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_history_load_still_ends_the_page() {
+    let (_systemd, mut recv, commands) = start(&[("alpha", "active")]).await;
+    send(
+        &commands,
+        "alpha",
+        Command::LoadLogHistory {
+            source: LogSource::Host,
+            range: LogRange {
+                since: Some("not a time".to_string()),
+                ..LogRange::default()
+            },
+            before: None,
+        },
+    );
+    expect_updates(
+        &mut recv,
+        "alpha",
+        &[
+            |update| matches!(update, Update::Error(_)),
+            |update| {
+                matches!(
+                    update,
+                    Update::LogHistory(page)
+                        if page.entries.is_empty()
+                            && !page.complete
+                            && page.range.since.as_deref() == Some("not a time")
+                )
+            },
+        ],
+    )
+    .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn commands_fail_while_disconnected() {
+    let (mut systemd, mut recv, commands) = start(&[("alpha", "active")]).await;
+    systemd.stop_bus();
+    expect_update(&mut recv, "systemd connection", |update| {
+        matches!(update, Update::Connected(false))
+    })
+    .await;
+    send(&commands, "alpha", Command::Unit(UnitOperation::Stop));
+    expect_update(&mut recv, "alpha", |update| {
+        matches!(update, Update::Error(error) if format!("{error:#}").ends_with("Disconnected from systemd"))
+    })
+    .await;
+}
+
+#[test]
+fn create_args_only_pass_network_settings_for_private_networks() {
+    let mut options = NewContainer {
+        private_network: false,
+        host_address: Some("10.233.1.1".to_string()),
+        local_address: Some("10.233.1.2".to_string()),
+        bridge: None,
+        source: Some(ContainerSource::Flake("github:owner/repo#web".to_string())),
+    };
+    let container = ContainerId::new("web");
+    assert_eq!(
+        create_args(&container, &options),
+        ["create", "web", "--flake", "github:owner/repo#web"]
+    );
+    options.private_network = true;
+    options.source = Some(ContainerSource::Config(
+        "services.nginx.enable = true;".to_string(),
+    ));
+    assert_eq!(
+        create_args(&container, &options),
+        [
+            "create",
+            "web",
+            "--host-address",
+            "10.233.1.1",
+            "--local-address",
+            "10.233.1.2",
+            "--config",
+            "services.nginx.enable = true;",
+        ]
+    );
+}
+
+#[test]
+fn update_args_pass_new_configuration() {
+    let container = ContainerId::new("web");
+    assert_eq!(update_args(&container, None), ["update", "web"]);
+    assert_eq!(
+        update_args(
+            &container,
+            Some(&ContainerSource::ConfigFile(PathBuf::from("/etc/web.nix")))
+        ),
+        ["update", "web", "--config-file", "/etc/web.nix"]
+    );
+}
+
+#[test]
+fn journal_entries_survive_bad_optional_fields() {
+    let entry = parse_entry(
+        r#"{"__REALTIME_TIMESTAMP":"1700000000000000","__CURSOR":"s=1","PRIORITY":"12","_PID":"x","MESSAGE":"hello"}"#,
+    )
+    .expect("Entry should parse");
+    assert_eq!(entry.message, "hello");
+    assert_eq!(entry.priority, None);
+    assert_eq!(entry.pid, None);
+    let entry = parse_entry(
+        r#"{"__REALTIME_TIMESTAMP":"1700000000000000","__CURSOR":"s=2","PRIORITY":"3","_PID":"42","MESSAGE":"oops"}"#,
+    )
+    .expect("Entry should parse");
+    assert_eq!(entry.priority, Some(Priority::Error));
+    assert_eq!(entry.pid, Some(42));
+}
+
+/// Parse a container config, formatting its errors with their context
+fn config_errors(contents: &str) -> Vec<String> {
+    let (_, errors) = parse_config(contents);
+    errors.iter().map(|error| format!("{error:#}")).collect()
+}
+
+#[test]
+fn config_parser_reports_malformed_values() {
+    for (contents, expected) in [
+        (
+            "PRIVATE_NETWORK=yes",
+            "Invalid setting PRIVATE_NETWORK: Expected 0 or 1, got yes",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nHOST_ADDRESS=10.0.0.300",
+            "Invalid setting HOST_ADDRESS: Invalid IP address 10.0.0.300: invalid IP address syntax",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nLOCAL_ADDRESS=fd00::2",
+            "Invalid setting LOCAL_ADDRESS: Expected an IPv4 address, got fd00::2",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nHOST_ADDRESS=10.0.0.1/33",
+            "Invalid setting HOST_ADDRESS: Invalid prefix length 33",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nHOST_ADDRESS6=10.0.0.1",
+            "Invalid setting HOST_ADDRESS6: Expected an IPv6 address, got 10.0.0.1",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nLOCAL_ADDRESS6=fd00::2/129",
+            "Invalid setting LOCAL_ADDRESS6: Invalid prefix length 129",
+        ),
+        (
+            "PRIVATE_NETWORK=1\nLOCAL_ADDRESS6=fd00::2/x",
+            "Invalid setting LOCAL_ADDRESS6: Invalid prefix length x",
+        ),
+        (
+            "HOST_PORT=tcp:80:8080 udp",
+            "Invalid setting HOST_PORT: Port forward udp is not of expected form",
+        ),
+        (
+            "HOST_PORT=tcp:80:8080:1",
+            "Invalid setting HOST_PORT: Port forward tcp:80:8080:1 is not of expected form",
+        ),
+        (
+            "HOST_PORT=tcp:99999",
+            "Invalid setting HOST_PORT: Invalid port 99999: number too large to fit in target type",
+        ),
+        (
+            "HOST_PORT=udp:53:dns",
+            "Invalid setting HOST_PORT: Invalid port dns: invalid digit found in string",
+        ),
+        (
+            "PRIVATE_NETWORK=0\nHOST_BRIDGE=br0",
+            "Network addresses or bridge are set but PRIVATE_NETWORK is disabled",
+        ),
+    ] {
+        assert_eq!(config_errors(contents), [expected], "{contents}");
+    }
+}
+
+#[test]
+fn config_parser_keeps_valid_settings_beside_invalid_ones() {
+    let (config, errors) = parse_config(
+        "# Comment\n\
+         PRIVATE_NETWORK=1\n\
+         AUTO_START=maybe\n\
+         HOST_ADDRESS=10.233.1.1\n\
+         LOCAL_ADDRESS6=\"fd00::2/64\"\n\
+         HOST_PORT=\"tcp:8080:80,udp:53 tcp:x\"\n\
+         INTERFACES=\" eth1  eth2 \"\n\
+         MACVLANS=eth3\n\
+         CUSTOM=value\n",
+    );
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(config.private_network);
+    assert!(!config.auto_start);
+    assert_eq!(
+        config.host_address.map(|address| address.to_string()),
+        Some("10.233.1.1".to_string())
+    );
+    assert_eq!(
+        config.local_address6.map(|address| address.to_string()),
+        Some("fd00::2/64".to_string())
+    );
+    assert!(config.host_ports.is_empty());
+    assert_eq!(config.interfaces, ["eth1", "eth2"]);
+    assert_eq!(config.macvlans, ["eth3"]);
+    assert_eq!(config.other["CUSTOM"], "value");
+
+    let (config, errors) = parse_config("HOST_PORT=\"tcp:8080:80,udp:53\"");
+    assert!(errors.is_empty(), "{errors:?}");
+    let ports = config
+        .host_ports
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(ports, ["tcp:8080 -> 80", "udp:53 -> 53"]);
+}
@@ -0,0 +1,449 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+use zbus::{Address, Connection, fdo, interface};
+
+/// Path of the systemd manager object
+const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+
+/// Configuration of the private bus, letting anyone own names and talk to
+/// anyone
+const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-BUS Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:dir=DIRECTORY</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+/// Fake nixos-container, destroying containers by deleting their configs
+const NIXOS_CONTAINER: &str = r#"#!/bin/sh
+case "$1" in
+  destroy) rm "DIRECTORY/containers/$2.conf" && echo "Destroyed $2" ;;
+  *) echo "Unsupported subcommand $1" >&2; exit 1 ;;
+esac
+"#;
+
+/// Counter keeping the directories of concurrent tests apart
+static NEXT_DIRECTORY: AtomicUsize = AtomicUsize::new(0);
+
+/// Fake systemd serving container units on a private `dbus-daemon`
+///
+/// Jobs queued on the manager stay pending until the test finishes them,
+/// so tests decide when and how each job ends.
+pub struct MockSystemd {
+    daemon: Child,
+    address: String,
+    /// Scratch directory holding the bus socket and the container configs
+    directory: PathBuf,
+    /// Connection owning the systemd name
+    service: Connection,
+}
+
+impl MockSystemd {
+    /// Start a private bus with a fake systemd manager on it
+    pub async fn start() -> Self {
+        let directory = std::env::temp_dir().join(format!(
+            "nixos-container-tui-{}-{}",
+            std::process::id(),
+            NEXT_DIRECTORY.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(directory.join("containers"))
+            .expect("Test directory should be creatable");
+        let config = directory.join("bus.conf");
+        fs::write(
+            &config,
+            BUS_CONFIG.replace("DIRECTORY", &directory.to_string_lossy()),
+        )
+        .expect("Bus config should be writable");
+        let nixos_container = directory.join("nixos-container");
+        fs::write(
+            &nixos_container,
+            NIXOS_CONTAINER.replace("DIRECTORY", &directory.to_string_lossy()),
+        )
+        .expect("Fake nixos-container should be writable");
+        fs::set_permissions(&nixos_container, fs::Permissions::from_mode(0o755))
+            .expect("Fake nixos-container should be executable");
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args(["--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon should be installed to run backend tests");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().expect("Stdout should be piped"))
+            .read_line(&mut address)
+            .expect("dbus-daemon should print its address");
+        let address = address.trim().to_string();
+        let service = zbus::connection::Builder::address(address.as_str())
+            .expect("Bus address should be valid")
+            .name("org.freedesktop.systemd1")
+            .expect("Systemd name should be valid")
+            .serve_at(MANAGER_PATH, Manager::default())
+            .expect("Manager path should be valid")
+            .build()
+            .await
+            .expect("Fake systemd should connect to the private bus");
+        Self {
+            daemon,
+            address,
+            directory,
+            service,
+        }
+    }
+
+    /// Get the address of the private bus, for clients to connect to
+    pub fn address(&self) -> Address {
+        self.address.parse().expect("Bus address should be valid")
+    }
+
+    /// Stop the private bus, as if the system bus went away
+    pub fn stop_bus(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+
+    /// Get the fake nixos-container program
+    pub fn nixos_container(&self) -> PathBuf {
+        self.directory.join("nixos-container")
+    }
+
+    /// Directory to write container configs into
+    pub fn config_dir(&self) -> PathBuf {
+        self.directory.join("containers")
+    }
+
+    /// Add the config and service unit of a container, in the given active
+    /// state
+    pub async fn add_container(&self, name: &str, active_state: &str) {
+        let unit_name = format!("container@{name}.service");
+        let manager = self.manager().await;
+        let path = {
+            let mut manager = manager.get_mut().await;
+            let path = ObjectPath::try_from(format!("{MANAGER_PATH}/unit/{}", manager.units.len()))
+                .expect("Unit path should be valid")
+                .into();
+            manager.units.push((unit_name.clone(), path));
+            manager.units.last().expect("Unit was just added").1.clone()
+        };
+        let server = self.service.object_server();
+        server
+            .at(
+                &path,
+                Unit {
+                    description: format!("Container '{name}'"),
+                    active_state: active_state.to_string(),
+                    sub_state: sub_state(active_state).to_string(),
+                },
+            )
+            .await
+            .expect("Unit should be served");
+        server
+            .at(&path, Service)
+            .await
+            .expect("Service should be served");
+        fs::write(
+            self.config_dir().join(format!("{name}.conf")),
+            "PRIVATE_NETWORK=1\nHOST_ADDRESS=10.233.1.1\nLOCAL_ADDRESS=10.233.1.2\n",
+        )
+        .expect("Container config should be writable");
+    }
+
+    /// Finish the oldest pending job with a result, moving its unit to the
+    /// state the job leads to if it is done
+    pub async fn finish_job(&self, result: &str) {
+        let manager = self.manager().await;
+        let job = manager
+            .get_mut()
+            .await
+            .jobs
+            .pop_front()
+            .expect("A job should be pending");
+        if result == "done" {
+            let state = match job.kind {
+                "stop" => "inactive",
+                _ => "active",
+            };
+            let path = manager
+                .get()
+                .await
+                .unit_path(&job.unit)
+                .expect("Unit should exist");
+            self.set_state(&path, state).await;
+        }
+        let emitter = manager.signal_emitter();
+        Manager::job_removed(emitter, job.id, job.path.as_ref(), &job.unit, result)
+            .await
+            .expect("Job removal should be signalled");
+    }
+
+    /// Get the kinds of the pending jobs, oldest first
+    pub async fn pending_jobs(&self) -> Vec<&'static str> {
+        let manager = self.manager().await;
+        let manager = manager.get().await;
+        manager.jobs.iter().map(|job| job.kind).collect()
+    }
+
+    /// Change the active state of a unit, signalling the change
+    async fn set_state(&self, path: &ObjectPath<'_>, active_state: &str) {
+        let unit = self
+            .service
+            .object_server()
+            .interface::<_, Unit>(path)
+            .await
+            .expect("Unit should be served");
+        {
+            let mut unit = unit.get_mut().await;
+            unit.active_state = active_state.to_string();
+            unit.sub_state = sub_state(active_state).to_string();
+        }
+        let emitter = unit.signal_emitter();
+        let unit = unit.get().await;
+        unit.active_state_changed(emitter)
+            .await
+            .expect("State change should be signalled");
+        unit.sub_state_changed(emitter)
+            .await
+            .expect("State change should be signalled");
+    }
+
+    async fn manager(&self) -> zbus::object_server::InterfaceRef<Manager> {
+        self.service
+            .object_server()
+            .interface::<_, Manager>(MANAGER_PATH)
+            .await
+            .expect("Manager should be served")
+    }
+}
+
+impl Drop for MockSystemd {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Sub-state systemd reports along with an active state
+fn sub_state(active_state: &str) -> &'static str {
+    match active_state {
+        "active" => "running",
+        "failed" => "failed",
+        _ => "dead",
+    }
+}
+
+/// Fake `org.freedesktop.systemd1.Manager`
+#[derive(Default)]
+struct Manager {
+    /// Paths of the units, by unit name
+    units: Vec<(String, OwnedObjectPath)>,
+    /// Jobs waiting for the test to finish them
+    jobs: VecDeque<Job>,
+    /// Number of jobs queued so far
+    queued: u32,
+}
+
+/// A queued job
+struct Job {
+    id: u32,
+    path: OwnedObjectPath,
+    unit: String,
+    kind: &'static str,
+}
+
+impl Manager {
+    fn unit_path(&self, unit_name: &str) -> Option<OwnedObjectPath> {
+        self.units
+            .iter()
+            .find(|(name, _)| name == unit_name)
+            .map(|(_, path)| path.clone())
+    }
+
+    fn queue_job(&mut self, unit_name: &str, kind: &'static str) -> fdo::Result<OwnedObjectPath> {
+        if self.unit_path(unit_name).is_none() {
+            return Err(fdo::Error::Failed(format!("Unit {unit_name} not found.")));
+        }
+        self.queued += 1;
+        let path = ObjectPath::try_from(format!("{MANAGER_PATH}/job/{}", self.queued))
+            .expect("Job path should be valid")
+            .into();
+        self.jobs.push_back(Job {
+            id: self.queued,
+            path: OwnedObjectPath::clone(&path),
+            unit: unit_name.to_string(),
+            kind,
+        });
+        Ok(path)
+    }
+}
+
+#[interface(name = "org.freedesktop.systemd1.Manager")]
+impl Manager {
+    async fn subscribe(&self) {}
+
+    async fn load_unit(&self, name: &str) -> fdo::Result<OwnedObjectPath> {
+        self.unit_path(name)
+            .ok_or_else(|| fdo::Error::Failed(format!("Unit {name} not found.")))
+    }
+
+    async fn start_unit(&mut self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+        self.queue_job(name, "start")
+    }
+
+    async fn stop_unit(&mut self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+        self.queue_job(name, "stop")
+    }
+
+    async fn restart_unit(&mut self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+        self.queue_job(name, "restart")
+    }
+
+    async fn try_restart_unit(&mut self, name: &str, _mode: &str) -> fdo::Result<OwnedObjectPath> {
+        self.queue_job(name, "try-restart")
+    }
+
+    async fn reload_or_restart_unit(
+        &mut self,
+        name: &str,
+        _mode: &str,
+    ) -> fdo::Result<OwnedObjectPath> {
+        self.queue_job(name, "reload-or-restart")
+    }
+
+    #[zbus(signal)]
+    async fn job_removed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        job: ObjectPath<'_>,
+        unit: &str,
+        result: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Fake `org.freedesktop.systemd1.Unit` of a container service
+struct Unit {
+    description: String,
+    active_state: String,
+    sub_state: String,
+}
+
+#[interface(name = "org.freedesktop.systemd1.Unit")]
+impl Unit {
+    #[zbus(property)]
+    async fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    #[zbus(property)]
+    async fn load_state(&self) -> String {
+        "loaded".to_string()
+    }
+
+    #[zbus(property)]
+    async fn active_state(&self) -> String {
+        self.active_state.clone()
+    }
+
+    #[zbus(property)]
+    async fn sub_state(&self) -> String {
+        self.sub_state.clone()
+    }
+
+    #[zbus(property)]
+    async fn active_enter_timestamp(&self) -> u64 {
+        0
+    }
+
+    #[zbus(property, name = "InvocationID")]
+    async fn invocation_id(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    async fn unit_file_state(&self) -> String {
+        "static".to_string()
+    }
+
+    #[zbus(property)]
+    async fn fragment_path(&self) -> String {
+        "/etc/systemd/system/container@.service".to_string()
+    }
+
+    #[zbus(property)]
+    async fn need_daemon_reload(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    async fn load_error(&self) -> (String, String) {
+        (String::new(), String::new())
+    }
+
+    #[zbus(property)]
+    async fn requires(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[zbus(property)]
+    async fn wants(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Fake `org.freedesktop.systemd1.Service` with only memory accounting
+struct Service;
+
+/// Memory usage reported for every container
+pub const MEMORY_CURRENT: u64 = 64 << 20;
+
+#[interface(name = "org.freedesktop.systemd1.Service")]
+impl Service {
+    #[zbus(property)]
+    async fn memory_current(&self) -> u64 {
+        MEMORY_CURRENT
+    }
+
+    #[zbus(property, name = "CPUUsageNSec")]
+    async fn cpu_usage_nsec(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property)]
+    async fn tasks_current(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property, name = "IOReadBytes")]
+    async fn io_read_bytes(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property, name = "IOWriteBytes")]
+    async fn io_write_bytes(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property, name = "IPIngressBytes")]
+    async fn ip_ingress_bytes(&self) -> u64 {
+        u64::MAX
+    }
+
+    #[zbus(property, name = "IPEgressBytes")]
+    async fn ip_egress_bytes(&self) -> u64 {
+        u64::MAX
+    }
+}
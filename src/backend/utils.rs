@@ -1,11 +1,22 @@
+use super::containers::CONTAINER_STATE_DIR;
+use super::messages::ContainerId;
+use std::path::{Path, PathBuf};
+
 // Re-export macros to get them in the right place
 pub use crate::{log, report_async};
 
 /// Get the systemd service name for a container
-pub fn service_name(container: &str) -> String {
+pub fn service_name(container: &ContainerId) -> String {
     format!("container@{container}.service")
 }
 
+/// Get the directory on the host holding a container's own journal
+pub fn guest_journal_dir(container: &ContainerId) -> PathBuf {
+    Path::new(CONTAINER_STATE_DIR)
+        .join(container.name())
+        .join("var/log/journal")
+}
+
 /// Helper for running a fallable async function and reporting returned errors
 #[macro_export]
 macro_rules! report_async {
@@ -17,24 +28,24 @@ macro_rules! report_async {
     ) => {
         $( #[ $meta ] )*
         $vis async fn $name (
-            container: &'static str,
+            container: ContainerId,
             channel: Sender,
             $( $arg_name : $ty , )*
         ) {
             let inner = async |
-                $container : &'static str,
+                $container : ContainerId,
                 $channel : Sender,
                 $( $arg_name : $ty , )*
             | -> Result<()> { $body };
             match inner(
-                container,
+                container.clone(),
                 channel.clone(),
                 $( $arg_name , )*
             ).await.context( $context ) {
                 Ok(()) => (),
                 Err(error) => channel
                     .send(NamedUpdate {
-                        container_name: container,
+                        container,
                         inner: Update::Error(error),
                     })
                     .expect("Channel should always be open"),
@@ -49,7 +60,7 @@ macro_rules! log {
     ($name:expr, $sender:expr, $message:expr) => {
         $sender
             .send(NamedUpdate {
-                container_name: $name,
+                container: $name.clone(),
                 inner: Update::Log(format!($message)),
             })
             .unwrap()
@@ -1,7 +1,14 @@
-use backend::messages::{ContainerState, NamedUpdate, Update};
+use backend::CommandSender;
+use backend::messages::{
+    Command, ContainerId, ContainerState, JobResult, NamedCommand, NamedUpdate, OperationOutcome,
+    UnitOperation, Update,
+};
 use cursive::Cursive;
+use std::env;
+use std::path::PathBuf;
 use tokio::task;
-use tui::Main;
+use tui::{ContainerRebuild, Main, ResourceGraphs, UnitDetails};
+use zbus::Address;
 
 /// Backend for communicating with systemd over dbus
 mod backend;
@@ -12,87 +19,203 @@ mod tui;
 #[tokio::main]
 async fn main() {
     // Start the backend
-    let (mut recv, containers, send) = backend::start_backend().await.unwrap();
+    let address = Address::system().expect("Could not find the system DBus");
+    let (mut recv, containers, backend) = backend::start_backend(
+        address,
+        PathBuf::from(backend::CONTAINER_CONFIG_DIR),
+        PathBuf::from("nixos-container"),
+    )
+    .await
+    .unwrap();
 
     // Create the TUI
     let mut root = cursive::default();
-    root.set_user_data(send);
-    Main::create(&mut root, &containers);
+    root.set_user_data(backend);
+    let line_cap = log_line_cap(env::var(LOG_LINES_VAR).ok().as_deref());
+    Main::create(
+        &mut root,
+        &containers,
+        *line_cap.as_ref().unwrap_or(&DEFAULT_LOG_LINES),
+    );
+    if let Err(warning) = line_cap {
+        Main::get_self(&mut root)
+            .get_debug_log()
+            .log(&ContainerId::new(LOG_LINES_VAR), &warning);
+    }
 
-    // Run the Cursive event loop, which checking for and
-    // handling backend messages
-    let mut runner = root.runner();
-    loop {
-        // Cursive event loop
-        let cursive_refresh = runner.step();
-        if !runner.is_running() {
-            break;
+    // Forward backend messages to the TUI, to be handled as callbacks
+    // between input events
+    let sink = root.cb_sink().clone();
+    task::spawn(async move {
+        while let Some(message) = recv.recv().await {
+            let callback = Box::new(move |root: &mut Cursive| handle_message(root, message));
+            // Stop once the TUI has quit
+            if sink.send(callback).is_err() {
+                break;
+            }
         }
+    });
+
+    // Run the Cursive event loop, which sleeps while there is nothing to do,
+    // on this thread while backend tasks run on the others
+    task::block_in_place(|| root.run());
+}
 
-        // Backend messages
-        let backend_refresh = if let Ok(msg) = recv.try_recv() {
-            handle_message(&mut runner, msg);
-            true
-        } else {
-            false
-        };
+/// Environment variable setting how many log lines are kept per container
+const LOG_LINES_VAR: &str = "NIXOS_CONTAINER_TUI_LOG_LINES";
 
-        // Refresh if anything happened
-        if cursive_refresh || backend_refresh {
-            runner.refresh();
-        }
+/// How many log lines are kept per container by default
+const DEFAULT_LOG_LINES: usize = 10_000;
 
-        // Give backend tasks a chance to run
-        task::yield_now().await;
+/// Get how many log lines to keep per container from the value of
+/// [`LOG_LINES_VAR`], or a warning if it is invalid
+fn log_line_cap(lines: Option<&str>) -> Result<usize, String> {
+    let Some(lines) = lines else {
+        return Ok(DEFAULT_LOG_LINES);
+    };
+    match lines.trim().parse() {
+        Ok(0) | Err(_) => Err(format!(
+            "Expected a positive number of lines, got {lines:?}, keeping {DEFAULT_LOG_LINES}"
+        )),
+        Ok(lines) => Ok(lines),
     }
 }
 
 /// Update the TUI given a backend message
 fn handle_message(root: &mut Cursive, message: NamedUpdate) {
     let main = Main::get_self(root);
-    let container_list = main.get_container_list();
-    let controls = container_list.get_container(message.container_name);
     match message.inner {
         Update::State(state) => {
+            // Ignore late updates from destroyed containers
+            let Some(controls) = main.get_container_list().get_container(&message.container) else {
+                return;
+            };
             // Get updated settings for state button
-            let (text, enabled, action): (_, _, &(dyn Fn(&mut _, _) + Sync)) = match state {
-                ContainerState::Up => ("UP", true, &stop_container),
-                ContainerState::Down => ("DOWN", true, &start_container),
-                ContainerState::Starting => ("STARTING", false, &|_, _| {}),
-                ContainerState::Stopping => ("STOPPING", false, &|_, _| {}),
-                ContainerState::Reloading => ("RELOADING", false, &|_, _| {}),
-                ContainerState::Refreshing => ("REFRESHING", false, &|_, _| {}),
-                ContainerState::Failed => ("FAILED", true, &start_container),
-                ContainerState::Maintenance => ("MAINTENANCE", true, &|_, _| {}),
+            let (text, operation) = match state {
+                ContainerState::Up => ("UP", Some(UnitOperation::Stop)),
+                ContainerState::Down => ("DOWN", Some(UnitOperation::Start)),
+                ContainerState::Starting => ("STARTING", None),
+                ContainerState::Stopping => ("STOPPING", None),
+                ContainerState::Reloading => ("RELOADING", None),
+                ContainerState::Refreshing => ("REFRESHING", None),
+                ContainerState::Failed => ("FAILED", Some(UnitOperation::Start)),
+                ContainerState::Maintenance => ("MAINTENANCE", None),
             };
             // Update button
             let state_button = controls.get_state_button();
             state_button.set_label(text);
-            state_button.set_enabled(enabled);
-            state_button.set_callback(move |root| action(root, message.container_name));
+            state_button.set_enabled(operation.is_some());
+            let container = message.container;
+            state_button.set_callback(move |root| {
+                if let Some(operation) = operation {
+                    send_command(root, container.clone(), Command::Unit(operation));
+                }
+            });
+        }
+        Update::JobFinished(result) => {
+            let Some(controls) = main.get_container_list().get_container(&message.container) else {
+                return;
+            };
+            let text = match result {
+                JobResult::Done => "done",
+                JobResult::Canceled => "canceled",
+                JobResult::Timeout => "timeout",
+                JobResult::Failed => "failed",
+                JobResult::Dependency => "dependency failed",
+                JobResult::Skipped => "skipped",
+            };
+            controls
+                .get_job_result()
+                .set_content(format!(" Last job: {text}"));
+        }
+        Update::ContainerAdded(container) => {
+            main.get_container_list().add_container(&container.id);
+            main.get_container_log().add_container(&container.id);
+            main.get_container_details().add_container(*container);
+            main.get_debug_log()
+                .log(&message.container, "Container created");
+        }
+        Update::ContainerRemoved => {
+            main.get_container_list()
+                .remove_container(&message.container);
+            main.get_container_details()
+                .remove_container(&message.container);
+            main.get_container_log()
+                .remove_container(&message.container);
+            main.get_debug_log()
+                .log(&message.container, "Container destroyed");
+        }
+        Update::ContainerChanged(container) => {
+            main.get_container_details().update_container(*container);
+            main.get_debug_log()
+                .log(&message.container, "Container config changed");
+        }
+        Update::UnitProperties(properties) => {
+            if let Some(controls) = main.get_container_list().get_container(&message.container) {
+                controls.set_properties((*properties).clone());
+            }
+            UnitDetails::refresh(root, &message.container, &properties);
+        }
+        Update::Resources(usage) => {
+            if let Some(controls) = main.get_container_list().get_container(&message.container) {
+                controls.set_resources(&usage);
+            }
         }
-        Update::ContainerLog(log) => main.get_container_log().log(message.container_name, log),
-        Update::Log(log) => main.get_debug_log().log(message.container_name, &log),
-        Update::Error(error) => main.get_debug_log().error(message.container_name, error),
+        Update::ResourceHistory(history) => {
+            ResourceGraphs::refresh(root, &message.container, &history);
+            let main = Main::get_self(root);
+            if let Some(controls) = main.get_container_list().get_container(&message.container) {
+                controls.set_history(*history);
+            }
+        }
+        Update::ContainerLog(source, log) => {
+            main.get_container_log()
+                .log(&message.container, &source, &log)
+        }
+        Update::LogHistory(page) => main
+            .get_container_log()
+            .add_history(&message.container, *page),
+        Update::Connected(connected) => {
+            main.set_connected(connected);
+            let log = if connected {
+                "Reconnected"
+            } else {
+                "Disconnected, reconnecting"
+            };
+            main.get_debug_log().log(&message.container, log);
+        }
+        Update::Operation(operation, outcome) => {
+            let log = match outcome {
+                OperationOutcome::Queued => format!("Queued {operation} behind running operation"),
+                OperationOutcome::Rejected => format!("Ignored {operation}, already pending"),
+                OperationOutcome::Finished => format!("Finished {operation}"),
+            };
+            main.get_debug_log().log(&message.container, &log);
+        }
+        Update::BuildOutput(line) => ContainerRebuild::output(root, &message.container, line),
+        Update::BuildSucceeded => {
+            main.get_debug_log()
+                .log(&message.container, "Rebuilt container");
+            ContainerRebuild::finish(root, &message.container, None);
+        }
+        Update::BuildFailed(error) => {
+            ContainerRebuild::finish(root, &message.container, Some(&error));
+            Main::get_self(root)
+                .get_debug_log()
+                .error(&message.container, error);
+        }
+        Update::Log(log) => main.get_debug_log().log(&message.container, &log),
+        Update::Error(error) => main.get_debug_log().error(&message.container, error),
     }
 }
 
-fn start_container(root: &mut Cursive, container_name: &'static str) {
-    task::spawn(backend::start_container(
-        container_name,
-        get_backend_channel(root),
-    ));
-}
-
-fn stop_container(root: &mut Cursive, container_name: &'static str) {
-    task::spawn(backend::stop_container(
-        container_name,
-        get_backend_channel(root),
-    ));
-}
-
-fn get_backend_channel(root: &mut Cursive) -> backend::Sender {
-    root.user_data::<backend::Sender>()
-        .expect("Backend channel should be in user data")
-        .clone()
+/// Send a command about a container to the backend
+fn send_command(root: &mut Cursive, container: ContainerId, command: Command) {
+    root.user_data::<CommandSender>()
+        .expect("Backend command sender should be in user data")
+        .send(NamedCommand {
+            container,
+            inner: command,
+        })
+        .expect("Backend should always be running");
 }
@@ -1,18 +1,90 @@
+use super::utils::bytes;
+use super::{ContainerDestroy, ResourceGraphs, Sparkline, UnitDetails};
+use crate::backend::messages::{
+    Command, ContainerId, ResourceHistory, ResourceUsage, UnitOperation, UnitProperties,
+};
 use cursive::view::ViewWrapper;
-use cursive::views::{Button, LinearLayout};
+use cursive::views::{Button, LinearLayout, PaddedView, TextView};
 
 /// Wrapper for the contols of an individual container
 pub struct ContainerControls {
     inner: LinearLayout,
+    properties: Option<UnitProperties>,
+    history: ResourceHistory,
 }
 
 impl ContainerControls {
-    pub fn new() -> Self {
+    pub fn new(container: &ContainerId) -> Self {
         // Button that displays the container status and brings it up/down
         let status_button = Button::new("[Unknown]", |_| {});
+        // Buttons for the other unit operations
+        let restart_button = Button::new("Restart", {
+            let container = container.clone();
+            move |root| {
+                crate::send_command(
+                    root,
+                    container.clone(),
+                    Command::Unit(UnitOperation::Restart),
+                )
+            }
+        });
+        let try_restart_button = Button::new("Try-restart", {
+            let container = container.clone();
+            move |root| {
+                crate::send_command(
+                    root,
+                    container.clone(),
+                    Command::Unit(UnitOperation::TryRestart),
+                )
+            }
+        });
+        let reload_button = Button::new("Reload", {
+            let container = container.clone();
+            move |root| {
+                crate::send_command(
+                    root,
+                    container.clone(),
+                    Command::Unit(UnitOperation::ReloadOrRestart),
+                )
+            }
+        });
+        let unit_button = Button::new("Unit", {
+            let container = container.clone();
+            move |root| UnitDetails::open(root, &container)
+        });
+        let usage_button = Button::new("Usage", {
+            let container = container.clone();
+            move |root| ResourceGraphs::open(root, &container)
+        });
+        let destroy_button = Button::new("Destroy", {
+            let container = container.clone();
+            move |root| ContainerDestroy::open(root, &container)
+        });
+        // Result of the last job issued on the container
+        let job_result = TextView::new("");
+        // Sampled resource usage of the container
+        let resources = TextView::new("");
+        // Recent memory and CPU usage of the container
+        let memory_graph = PaddedView::lrtb(1, 0, 0, 0, Sparkline::new(20, 1));
+        let cpu_graph = PaddedView::lrtb(1, 0, 0, 0, Sparkline::new(20, 1));
         // Create inner view
-        let inner = LinearLayout::horizontal().child(status_button);
-        Self { inner }
+        let inner = LinearLayout::horizontal()
+            .child(status_button)
+            .child(restart_button)
+            .child(try_restart_button)
+            .child(reload_button)
+            .child(unit_button)
+            .child(usage_button)
+            .child(destroy_button)
+            .child(job_result)
+            .child(resources)
+            .child(memory_graph)
+            .child(cpu_graph);
+        Self {
+            inner,
+            properties: None,
+            history: ResourceHistory::default(),
+        }
     }
 
     pub fn get_state_button(&mut self) -> &mut Button {
@@ -22,6 +94,75 @@ impl ContainerControls {
             .downcast_mut::<Button>()
             .expect("Container state button should be expected type")
     }
+
+    pub fn get_job_result(&mut self) -> &mut TextView {
+        self.inner
+            .get_child_mut(7)
+            .expect("Container job result should be present")
+            .downcast_mut::<TextView>()
+            .expect("Container job result should be expected type")
+    }
+
+    /// Show the latest resource usage of the container
+    pub fn set_resources(&mut self, usage: &ResourceUsage) {
+        let cpu = usage
+            .cpu_rate
+            .map_or_else(|| "-".to_string(), |rate| format!("{:.1}%", rate * 100.0));
+        let tasks = usage
+            .tasks
+            .map_or_else(|| "-".to_string(), |tasks| tasks.to_string());
+        let text = format!(
+            " mem {:>7} cpu {:>6} tasks {:>4} io {:>7}/{:<7} net {:>7}/{:<7}",
+            bytes(usage.memory),
+            cpu,
+            tasks,
+            bytes(usage.io_read),
+            bytes(usage.io_write),
+            bytes(usage.ip_ingress),
+            bytes(usage.ip_egress),
+        );
+        self.inner
+            .get_child_mut(8)
+            .expect("Container resource usage should be present")
+            .downcast_mut::<TextView>()
+            .expect("Container resource usage should be expected type")
+            .set_content(text);
+    }
+
+    /// Get the recent resource usage of the container
+    pub fn get_history(&self) -> &ResourceHistory {
+        &self.history
+    }
+
+    /// Show the recent resource usage of the container
+    pub fn set_history(&mut self, history: ResourceHistory) {
+        self.get_graph(9).set_values(
+            history
+                .memory
+                .iter()
+                .map(|memory| memory.map(|memory| memory as f64)),
+        );
+        self.get_graph(10).set_values(history.cpu.iter().copied());
+        self.history = history;
+    }
+
+    /// Get the last reported properties of the container service
+    pub fn get_properties(&self) -> Option<&UnitProperties> {
+        self.properties.as_ref()
+    }
+
+    pub fn set_properties(&mut self, properties: UnitProperties) {
+        self.properties = Some(properties);
+    }
+
+    fn get_graph(&mut self, index: usize) -> &mut Sparkline {
+        self.inner
+            .get_child_mut(index)
+            .expect("Container resource graph should be present")
+            .downcast_mut::<PaddedView<Sparkline>>()
+            .expect("Container resource graph should be expected type")
+            .get_inner_mut()
+    }
 }
 
 impl ViewWrapper for ContainerControls {
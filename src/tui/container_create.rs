@@ -0,0 +1,154 @@
+use crate::backend::messages::{Command, ContainerId, ContainerSource, NewContainer};
+use cursive::Cursive;
+use cursive::view::{Nameable, Resizable, ViewWrapper};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, RadioGroup, TextArea, TextView};
+use std::net::IpAddr;
+
+/// Dialog for creating a new container with `nixos-container create`
+pub struct ContainerCreate {
+    inner: Dialog,
+}
+
+/// Where the configuration of the new container comes from
+#[derive(Clone, Copy)]
+enum SourceChoice {
+    Empty,
+    Flake,
+    Config,
+}
+
+/// Longest container name nixos-container accepts, as the host side
+/// interface `ve-<name>` is limited to 15 characters
+const MAX_NAME_LENGTH: usize = 11;
+
+impl ContainerCreate {
+    /// Open the dialog for creating a container
+    pub fn open(root: &mut Cursive) {
+        let mut source = RadioGroup::new();
+        let field = |label, name| {
+            LinearLayout::horizontal()
+                .child(TextView::new(label).fixed_width(15))
+                .child(EditView::new().with_name(name).min_width(40))
+        };
+        let form = LinearLayout::vertical()
+            .child(field("Name", CREATE_NAME))
+            .child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().with_name(CREATE_PRIVATE_NETWORK))
+                    .child(TextView::new(" Private network")),
+            )
+            .child(field("Host address", CREATE_HOST_ADDRESS))
+            .child(field("Local address", CREATE_LOCAL_ADDRESS))
+            .child(field("Bridge", CREATE_BRIDGE))
+            .child(TextView::new("Configuration"))
+            .child(source.button(SourceChoice::Empty, "Empty"))
+            .child(source.button(SourceChoice::Flake, "Flake"))
+            .child(source.button(SourceChoice::Config, "Config snippet"))
+            .child(field("Flake", CREATE_FLAKE))
+            .child(TextArea::new().with_name(CREATE_CONFIG).min_size((55, 5)));
+        let create = Self {
+            inner: Dialog::around(form)
+                .title("New container")
+                .button("Create", move |root| {
+                    Self::create(root, *source.selection())
+                })
+                .dismiss_button("Cancel"),
+        };
+        root.add_layer(create);
+    }
+
+    /// Create the container described in the dialog, reporting invalid
+    /// settings
+    fn create(root: &mut Cursive, source: SourceChoice) {
+        let text = |root: &mut Cursive, name| {
+            root.call_on_name(name, |edit: &mut EditView| edit.get_content())
+                .expect("Create field should be present")
+                .trim()
+                .to_string()
+        };
+        let optional = |text: String| (!text.is_empty()).then_some(text);
+        let name = text(root, CREATE_NAME);
+        let private_network = root
+            .call_on_name(CREATE_PRIVATE_NETWORK, |checkbox: &mut Checkbox| {
+                checkbox.is_checked()
+            })
+            .expect("Private network checkbox should be present");
+        let source = match source {
+            SourceChoice::Empty => None,
+            SourceChoice::Flake => Some(ContainerSource::Flake(text(root, CREATE_FLAKE))),
+            SourceChoice::Config => Some(ContainerSource::Config(
+                root.call_on_name(CREATE_CONFIG, |area: &mut TextArea| {
+                    area.get_content().to_string()
+                })
+                .expect("Config field should be present"),
+            )),
+        };
+        let options = NewContainer {
+            private_network,
+            host_address: optional(text(root, CREATE_HOST_ADDRESS)),
+            local_address: optional(text(root, CREATE_LOCAL_ADDRESS)),
+            bridge: optional(text(root, CREATE_BRIDGE)),
+            source,
+        };
+        if let Err(error) = validate(&name, &options) {
+            root.add_layer(Dialog::info(error));
+            return;
+        }
+        root.pop_layer();
+        crate::send_command(root, ContainerId::new(&name), Command::Create(options));
+    }
+}
+
+impl ViewWrapper for ContainerCreate {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+/// Names of the create dialog fields
+const CREATE_NAME: &str = "container-create-name";
+const CREATE_PRIVATE_NETWORK: &str = "container-create-private-network";
+const CREATE_HOST_ADDRESS: &str = "container-create-host-address";
+const CREATE_LOCAL_ADDRESS: &str = "container-create-local-address";
+const CREATE_BRIDGE: &str = "container-create-bridge";
+const CREATE_FLAKE: &str = "container-create-flake";
+const CREATE_CONFIG: &str = "container-create-config";
+
+/// Check the settings of a new container, describing the first problem
+fn validate(name: &str, options: &NewContainer) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Enter a name for the container".to_string());
+    }
+    if name.len() > MAX_NAME_LENGTH
+        || !name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '-')
+    {
+        return Err(format!(
+            "Container names are up to {MAX_NAME_LENGTH} letters, digits and dashes"
+        ));
+    }
+    if options.private_network {
+        for address in [&options.host_address, &options.local_address]
+            .into_iter()
+            .flatten()
+        {
+            if address.parse::<IpAddr>().is_err() {
+                return Err(format!("Invalid address {address}"));
+            }
+        }
+        if options.local_address.is_none() {
+            return Err("A private network needs a local address".to_string());
+        }
+        if options.host_address.is_none() && options.bridge.is_none() {
+            return Err("A private network needs a host address or bridge".to_string());
+        }
+    }
+    match &options.source {
+        Some(ContainerSource::Flake(flake)) if flake.is_empty() => {
+            Err("Enter a flake reference".to_string())
+        }
+        Some(ContainerSource::Config(config)) if config.trim().is_empty() => {
+            Err("Enter a config snippet".to_string())
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,62 @@
+use super::Main;
+use crate::backend::messages::{Command, ContainerId};
+use cursive::Cursive;
+use cursive::view::{Nameable, Resizable, ViewWrapper};
+use cursive::views::{Dialog, EditView, LinearLayout, TextView};
+
+/// Dialog confirming a container should be destroyed, by having its name
+/// typed in
+pub struct ContainerDestroy {
+    inner: Dialog,
+}
+
+impl ContainerDestroy {
+    /// Open the dialog for destroying a container
+    pub fn open(root: &mut Cursive, container: &ContainerId) {
+        let Some(details) = Main::get_self(root).get_container_details().get(container) else {
+            return;
+        };
+        let form = LinearLayout::vertical()
+            .child(TextView::new(format!(
+                "This stops the container and deletes its state and config:\n\n\
+                 State root: {}\n\
+                 Config: {}\n\n\
+                 Type the container name to confirm.",
+                details.state_root.display(),
+                details.config_path.display(),
+            )))
+            .child(EditView::new().with_name(DESTROY_NAME).min_width(20));
+        let destroy = Self {
+            inner: Dialog::around(form)
+                .title(format!("Destroy container - {container}"))
+                .button("Destroy", {
+                    let container = container.clone();
+                    move |root| Self::destroy(root, &container)
+                })
+                .dismiss_button("Cancel"),
+        };
+        root.add_layer(destroy);
+    }
+
+    /// Destroy the container if its name was typed correctly
+    fn destroy(root: &mut Cursive, container: &ContainerId) {
+        let typed = root
+            .call_on_name(DESTROY_NAME, |edit: &mut EditView| edit.get_content())
+            .expect("Destroy confirmation field should be present");
+        if typed.trim() != container.name() {
+            root.add_layer(Dialog::info(format!(
+                "Type {container} to confirm destroying it"
+            )));
+            return;
+        }
+        root.pop_layer();
+        crate::send_command(root, container.clone(), Command::Destroy);
+    }
+}
+
+impl ViewWrapper for ContainerDestroy {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+/// Name of the destroy confirmation field
+const DESTROY_NAME: &str = "container-destroy-name";
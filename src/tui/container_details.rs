@@ -0,0 +1,113 @@
+use super::utils::{list, optional, yes_no};
+use crate::backend::messages::{Container, ContainerId};
+use cursive::view::ViewWrapper;
+use cursive::views::{Panel, ScrollView, TextView};
+use std::collections::HashMap;
+
+/// Wrapper for the config details of the selected container
+pub struct ContainerDetails {
+    inner: Panel<ScrollView<TextView>>,
+    containers: HashMap<ContainerId, Container>,
+    shown: Option<ContainerId>,
+}
+
+impl ContainerDetails {
+    pub fn new(containers: &Vec<Container>) -> Self {
+        let mut out = Self {
+            inner: Panel::new(ScrollView::new(TextView::new(""))).title("Details"),
+            containers: HashMap::new(),
+            shown: None,
+        };
+        for container in containers {
+            out.add_container(container.clone());
+        }
+        if let Some(container) = containers.first() {
+            out.show(container.id.name());
+        }
+        out
+    }
+
+    /// Add the details of a newly created container
+    pub fn add_container(&mut self, container: Container) {
+        self.containers.insert(container.id.clone(), container);
+    }
+
+    /// Replace the details of a container whose config was edited
+    pub fn update_container(&mut self, container: Container) {
+        let id = container.id.clone();
+        self.containers.insert(id.clone(), container);
+        if self.shown.as_ref() == Some(&id) {
+            self.show(id.name());
+        }
+    }
+
+    /// Get a container by its identifier
+    pub fn get(&self, container: &ContainerId) -> Option<&Container> {
+        self.containers.get(container)
+    }
+
+    /// Remove the details of a destroyed container
+    pub fn remove_container(&mut self, container: &ContainerId) {
+        self.containers.remove(container);
+        if self.shown.as_ref() == Some(container) {
+            self.shown = None;
+            self.inner.set_title("Details");
+            self.get_text().set_content("");
+        }
+    }
+
+    /// Show the details of a container
+    pub fn show(&mut self, container: &str) {
+        let Some(container) = self.containers.get(container) else {
+            return;
+        };
+        let config = &container.config;
+        let mut lines = vec![
+            format!("Config: {}", container.config_path.display()),
+            format!("Unit: {}", container.unit_name),
+            format!("State root: {}", container.state_root.display()),
+            format!(
+                "System: {}",
+                optional(config.system_path.as_ref().map(|path| path.display()))
+            ),
+            format!("Auto start: {}", yes_no(config.auto_start)),
+            format!("Private network: {}", yes_no(config.private_network)),
+            format!("Host bridge: {}", optional(config.host_bridge.as_ref())),
+            format!("Host address: {}", optional(config.host_address)),
+            format!("Local address: {}", optional(config.local_address)),
+            format!("Host IPv6 address: {}", optional(config.host_address6)),
+            format!("Local IPv6 address: {}", optional(config.local_address6)),
+            format!("Forwarded ports: {}", list(&config.host_ports)),
+            format!("Interfaces: {}", list(&config.interfaces)),
+            format!("Macvlans: {}", list(&config.macvlans)),
+            format!("Extra nspawn flags: {}", list(&config.extra_nspawn_flags)),
+            format!(
+                "Network namespace: {}",
+                optional(
+                    config
+                        .network_namespace_path
+                        .as_ref()
+                        .map(|path| path.display())
+                )
+            ),
+        ];
+        lines.extend(
+            config
+                .other
+                .iter()
+                .map(|(key, value)| format!("{key}: {value}")),
+        );
+        let title = format!("Details - {}", container.id);
+        self.shown = Some(container.id.clone());
+        self.inner.set_title(title);
+        self.get_text().set_content(lines.join("\n"));
+    }
+
+    fn get_text(&mut self) -> &mut TextView {
+        self.inner.get_inner_mut().get_inner_mut()
+    }
+}
+
+impl ViewWrapper for ContainerDetails {
+    cursive::wrap_impl!(self.inner: Panel<ScrollView<TextView>>);
+}
@@ -1,6 +1,8 @@
 use super::utils;
 use super::{ContainerControls, Main};
-use cursive::view::ViewWrapper;
+use crate::backend::messages::{Container, ContainerId};
+use cursive::direction::Direction;
+use cursive::view::{View, ViewWrapper};
 use cursive::views::{ListView, Panel, ScrollView};
 
 /// Wrapper for the main container list
@@ -9,26 +11,49 @@ pub struct ContainerList {
 }
 
 impl ContainerList {
-    /// Create a container list TUI from a list of container names
-    pub fn new(containers: &Vec<&'static str>) -> Self {
+    /// Create a container list TUI from a list of containers
+    pub fn new(containers: &Vec<Container>) -> Self {
         let mut list = ListView::new().on_select(|root, container| {
             let main = Main::get_self(root);
             main.get_container_log().show(container);
+            main.get_container_details().show(container);
         });
         for container in containers {
-            list.add_child(*container, ContainerControls::new());
+            list.add_child(container.id.name(), ContainerControls::new(&container.id));
         }
         Self {
             inner: Panel::new(ScrollView::new(list)).title("Containers"),
         }
     }
 
-    /// Get the container view for a given name
-    pub fn get_container(&mut self, name: &str) -> &mut ContainerControls {
-        utils::get_list_child(self.inner.get_inner_mut().get_inner_mut(), name)
-            .expect("Container name should be valid")
-            .downcast_mut()
-            .expect("Container view be of expected type")
+    /// Get the view for a given container, if it is still present
+    pub fn get_container(&mut self, container: &ContainerId) -> Option<&mut ContainerControls> {
+        utils::get_list_child(self.inner.get_inner_mut().get_inner_mut(), container.name()).map(
+            |view| {
+                view.downcast_mut()
+                    .expect("Container view be of expected type")
+            },
+        )
+    }
+
+    /// Add a row for a newly created container
+    pub fn add_container(&mut self, container: &ContainerId) {
+        self.inner
+            .get_inner_mut()
+            .get_inner_mut()
+            .add_child(container.name(), ContainerControls::new(container));
+    }
+
+    /// Remove the row of a destroyed container
+    pub fn remove_container(&mut self, container: &ContainerId) {
+        let list = self.inner.get_inner_mut().get_inner_mut();
+        if let Some(index) = utils::find_list_child(list, container.name()) {
+            list.remove_child(index);
+            // Keep the focus in range if the last row was removed
+            if list.focus() >= list.len() {
+                let _ = list.take_focus(Direction::back());
+            }
+        }
     }
 }
 
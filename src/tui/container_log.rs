@@ -1,63 +1,831 @@
-use cursive::view::{Nameable, ViewWrapper};
+use super::utils::timestamp;
+use super::{LogFilter, LogLine, LogView, Main};
+use crate::backend::messages::{
+    Command, Container, ContainerId, JournalEntry, LogPage, LogRange, LogSource, Priority,
+};
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
+use cursive::view::{Nameable, Resizable, ScrollStrategy, ViewWrapper};
 use cursive::views::stack_view::{Fullscreen, NoShadow};
 use cursive::views::{
-    FocusTracker, HideableView, LayerPosition, LinearLayout, NamedView, Panel, ScrollView,
-    StackView, TextView,
+    Checkbox, Dialog, EditView, FocusTracker, HideableView, LayerPosition, LinearLayout, ListView,
+    NamedView, Panel, ScrollView, SelectView, StackView, TextView,
 };
+use cursive::{Cursive, Rect};
+use regex::Regex;
+use std::collections::HashMap;
+use std::mem;
+
+/// Layer of the log of a single container
+type ContainerLayer = HideableView<NamedView<Panel<ScrollView<LogView<JournalEntry>>>>>;
+
+/// Layer of the merged log of several containers
+type MergedLayer = HideableView<NamedView<Panel<ScrollView<LogView<MergedEntry>>>>>;
+
+/// Name of the merged log layer, which no container can have
+const MERGED_LOG: &str = "(merged)";
+
+/// Colours distinguishing containers in the merged log, repeating only past
+/// this many containers, where lines are still told apart by their prefix
+const MERGED_COLOURS: [Color; 12] = [
+    Color::Light(BaseColor::Cyan),
+    Color::Light(BaseColor::Magenta),
+    Color::Light(BaseColor::Green),
+    Color::Light(BaseColor::Yellow),
+    Color::Light(BaseColor::Blue),
+    Color::Light(BaseColor::Red),
+    Color::Dark(BaseColor::Cyan),
+    Color::Dark(BaseColor::Magenta),
+    Color::Dark(BaseColor::Green),
+    Color::Dark(BaseColor::Yellow),
+    Color::Dark(BaseColor::Blue),
+    Color::Dark(BaseColor::Red),
+];
 
 pub struct ContainerLog {
     inner: FocusTracker<StackView>,
+    line_cap: usize,
+    /// What is shown of each container's log
+    states: HashMap<String, LogState>,
+    /// Containers shown in the merged log, with the colour each was given
+    merged: Vec<(ContainerId, Color)>,
 }
 
 impl ContainerLog {
-    pub fn new(containers: &Vec<&'static str>) -> Self {
-        let mut inner = StackView::new();
-        for container in containers {
-            inner.add_layer(Fullscreen(NoShadow(
-                HideableView::new(
-                    Panel::new(ScrollView::new(LinearLayout::vertical()))
-                        .title(format!("Logs - {container}"))
-                        .with_name(*container),
-                )
-                .hidden(),
-            )));
-        }
+    /// Create a log viewer keeping at most `line_cap` lines per container
+    pub fn new(containers: &Vec<Container>, line_cap: usize) -> Self {
         let mut out = Self {
-            inner: FocusTracker::new(inner),
+            inner: FocusTracker::new(StackView::new()),
+            line_cap,
+            states: HashMap::new(),
+            merged: Vec::new(),
         };
-        out.show(containers[0]);
+        out.inner.get_inner_mut().add_layer(Fullscreen(NoShadow(
+            HideableView::new(
+                Panel::new(ScrollView::new(LogView::<MergedEntry>::new(line_cap)).scroll_x(true))
+                    .title("Logs - merged")
+                    .with_name(MERGED_LOG),
+            )
+            .hidden(),
+        )));
+        for container in containers {
+            out.add_container(&container.id);
+        }
+        if let Some(container) = containers.first() {
+            out.show(container.id.name());
+        }
         out
     }
 
-    pub fn log(&mut self, container: &str, log: String) {
-        let layer = self.get_by_name(container);
+    /// Add a log layer for a container
+    pub fn add_container(&mut self, container: &ContainerId) {
+        let layer = Fullscreen(NoShadow(
+            HideableView::new(
+                Panel::new(
+                    ScrollView::new(LogView::<JournalEntry>::new(self.line_cap))
+                        .scroll_x(true)
+                        .on_scroll({
+                            let container = container.clone();
+                            move |root, viewport: Rect| {
+                                if viewport.top() == 0 {
+                                    Self::load_older(root, &container);
+                                }
+                            }
+                        }),
+                )
+                .title(format!("Logs - {container}"))
+                .with_name(container.name()),
+            )
+            .hidden(),
+        ));
+        // Add behind the currently shown layer
+        let stack = self.inner.get_inner_mut();
+        stack.add_layer(layer);
+        stack.move_layer(LayerPosition::FromFront(0), LayerPosition::FromFront(1));
+        self.states
+            .insert(container.name().to_string(), LogState::default());
+    }
+
+    /// Remove the log layer of a destroyed container
+    pub fn remove_container(&mut self, container: &ContainerId) {
+        let Some(layer) = self
+            .inner
+            .get_inner_mut()
+            .find_layer_from_name(container.name())
+        else {
+            return;
+        };
+        self.inner.get_inner_mut().remove_layer(layer);
+        self.states.remove(container.name());
+        self.merged.retain(|(merged, _)| merged != container);
+        // Show the next container if the removed one was shown
+        if let Some(layer) = self
+            .inner
+            .get_inner_mut()
+            .get_mut(LayerPosition::FromFront(0))
+            .and_then(|layer| layer.downcast_mut::<ContainerLayer>())
+        {
+            layer.unhide();
+        }
+    }
+
+    /// Add an entry read from one of a container's journals
+    pub fn log(&mut self, container: &ContainerId, source: &LogSource, entry: &JournalEntry) {
+        // Ignore late logs from destroyed containers
+        if !self.states.contains_key(container.name()) {
+            return;
+        }
+        if *source == LogSource::Host {
+            self.merge(container, entry);
+        }
+        let Some(state) = self.state(container.name()) else {
+            return;
+        };
+        // Keep live logs that are not shown up to date, ignoring late logs
+        // from sources no longer followed
+        if state.source != *source || state.history.is_some() {
+            if let Some(view) = state.hidden.get_mut(source) {
+                view.push(entry.clone());
+            }
+            return;
+        }
+        let Some(layer) = self.get_by_name(container.name()) else {
+            return;
+        };
         let mut inner = self.get(layer).get_inner_mut().get_mut();
         let scroll = inner.get_inner_mut();
         let follow = scroll.is_at_bottom();
-        scroll.get_inner_mut().add_child(TextView::new(log));
+        scroll.get_inner_mut().push(entry.clone());
         if follow {
-            scroll.scroll_to_bottom();
+            scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
         }
     }
 
     pub fn show(&mut self, container: &str) {
-        self.get(LayerPosition::FromFront(0)).hide();
-        let layer = self.get_by_name(container);
+        let Some(layer) = self.inner.get_inner_mut().find_layer_from_name(container) else {
+            return;
+        };
+        self.hide_shown();
         self.get(layer).unhide();
         self.inner.get_inner_mut().move_to_front(layer);
+        // Keep the merged log out of the way of containers being removed
+        let merged = self.merged_layer();
+        self.inner.get_inner_mut().move_to_back(merged);
     }
 
-    fn get_by_name(&mut self, container: &str) -> LayerPosition {
-        self.inner
+    /// Hide the shown log, whether of a container or merged
+    fn hide_shown(&mut self) {
+        let layer = self
+            .inner
+            .get_inner_mut()
+            .get_mut(LayerPosition::FromFront(0))
+            .expect("Merged log should be present");
+        match layer.downcast_mut::<ContainerLayer>() {
+            Some(layer) => layer.hide(),
+            None => layer
+                .downcast_mut::<MergedLayer>()
+                .expect("Log layer should be expected type")
+                .hide(),
+        }
+    }
+
+    /// Open a selector for the containers to show in the merged log
+    pub fn open_merge(root: &mut Cursive) {
+        let log = Main::get_self(root).get_container_log();
+        let mut containers = log.states.keys().cloned().collect::<Vec<_>>();
+        containers.sort();
+        let mut list = ListView::new();
+        for container in &containers {
+            let merged = log
+                .merged
+                .iter()
+                .any(|(merged, _)| merged.name() == container);
+            list.add_child(
+                container,
+                Checkbox::new()
+                    .with_checked(merged)
+                    .with_name(format!("merge-{container}")),
+            );
+        }
+        root.add_layer(
+            Dialog::around(ScrollView::new(list))
+                .title("Merge logs")
+                .button("Show", move |root| {
+                    let selected = containers
+                        .iter()
+                        .filter(|container| {
+                            root.call_on_name(
+                                &format!("merge-{container}"),
+                                |checkbox: &mut Checkbox| checkbox.is_checked(),
+                            )
+                            .unwrap_or(false)
+                        })
+                        .map(|container| ContainerId::new(container))
+                        .collect::<Vec<_>>();
+                    if selected.is_empty() {
+                        root.add_layer(Dialog::info("Select at least one container"));
+                        return;
+                    }
+                    root.pop_layer();
+                    Main::get_self(root)
+                        .get_container_log()
+                        .show_merged(selected);
+                })
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Show the interleaved logs of several containers
+    fn show_merged(&mut self, containers: Vec<ContainerId>) {
+        // Leave out containers destroyed while they were being selected
+        let containers = containers
+            .into_iter()
+            .filter(|container| self.states.contains_key(container.name()))
+            .zip(MERGED_COLOURS.into_iter().cycle())
+            .collect::<Vec<_>>();
+        if containers.is_empty() {
+            return;
+        }
+        let mut entries = Vec::new();
+        for (container, colour) in &containers {
+            let Some(state) = self.state(container.name()) else {
+                continue;
+            };
+            let host = match state.hidden.get(&LogSource::Host) {
+                Some(host) => host.items().cloned().collect::<Vec<_>>(),
+                None => {
+                    let Some(layer) = self.get_by_name(container.name()) else {
+                        continue;
+                    };
+                    let panel = self.get(layer).get_inner_mut().get_mut();
+                    panel.get_inner().get_inner().items().cloned().collect()
+                }
+            };
+            entries.extend(host.into_iter().map(|entry| MergedEntry {
+                container: container.clone(),
+                colour: *colour,
+                entry,
+            }));
+        }
+        // Stable, so entries logged at once by a container stay in order
+        entries.sort_by_key(|merged| merged.entry.timestamp);
+        let mut view = LogView::new(self.line_cap);
+        for entry in entries {
+            view.push(entry);
+        }
+        let names = containers
+            .iter()
+            .map(|(container, _)| container.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.merged = containers;
+        self.hide_shown();
+        let layer = self.merged_layer();
+        let merged = self.get_merged(layer);
+        merged.unhide();
+        let mut panel = merged.get_inner_mut().get_mut();
+        panel.set_title(format!("Logs - merged ({names})"));
+        let scroll = panel.get_inner_mut();
+        *scroll.get_inner_mut() = view;
+        scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+        drop(panel);
+        self.inner.get_inner_mut().move_to_front(layer);
+    }
+
+    /// Add a container's host log entry to the merged log if it is shown there
+    fn merge(&mut self, container: &ContainerId, entry: &JournalEntry) {
+        let Some(&(_, colour)) = self.merged.iter().find(|(merged, _)| merged == container) else {
+            return;
+        };
+        let layer = self.merged_layer();
+        let mut panel = self.get_merged(layer).get_inner_mut().get_mut();
+        let scroll = panel.get_inner_mut();
+        let follow = scroll.is_at_bottom();
+        scroll.get_inner_mut().insert_by_key(
+            MergedEntry {
+                container: container.clone(),
+                colour,
+                entry: entry.clone(),
+            },
+            |merged| merged.entry.timestamp,
+        );
+        if follow {
+            scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+        }
+    }
+
+    /// Open a prompt for filtering the shown container's log by journal fields
+    pub fn open_filter(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let prompt = EditView::new()
+            .on_submit({
+                let container = container.clone();
+                move |root, text| Self::apply_filter(root, &container, text)
+            })
+            .min_width(40);
+        root.add_layer(
+            Dialog::around(prompt)
+                .title(format!("Filter logs - {container} (FIELD=value ...)"))
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    fn apply_filter(root: &mut Cursive, container: &str, text: &str) {
+        match parse_field_filter(text) {
+            Ok(fields) => {
+                root.pop_layer();
+                Main::get_self(root)
+                    .get_container_log()
+                    .update_filter(container, |filter| filter.fields = fields);
+            }
+            Err(error) => root.add_layer(Dialog::info(error)),
+        }
+    }
+
+    /// Open a selector for the least severe priority shown in the shown
+    /// container's log
+    pub fn open_priority_threshold(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let mut selector = SelectView::new().on_submit({
+            let container = container.clone();
+            move |root, threshold: &Priority| {
+                root.pop_layer();
+                Main::get_self(root)
+                    .get_container_log()
+                    .update_filter(&container, |filter| filter.threshold = *threshold);
+            }
+        });
+        for priority in Priority::ALL {
+            selector.add_item(priority.name(), priority);
+        }
+        let Some(state) = Main::get_self(root).get_container_log().state(&container) else {
+            return;
+        };
+        selector.set_selection(state.filter.threshold as usize);
+        root.add_layer(
+            Dialog::around(selector)
+                .title(format!("Minimum priority - {container}"))
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Change what is shown of a container's log, unless it was destroyed
+    fn update_filter(&mut self, container: &str, update: impl FnOnce(&mut EntryFilter)) {
+        let Some(state) = self.state(container) else {
+            return;
+        };
+        update(&mut state.filter);
+        let filter = state.filter.clone();
+        for view in state.hidden.values_mut() {
+            view.set_filter(filter.log_filter());
+        }
+        let Some(layer) = self.get_by_name(container) else {
+            return;
+        };
+        let mut panel = self.get(layer).get_inner_mut().get_mut();
+        panel
+            .get_inner_mut()
             .get_inner_mut()
-            .find_layer_from_name(container)
-            .expect("Container name should be valid")
+            .set_filter(filter.log_filter());
+        drop(panel);
+        self.update_title(container);
+    }
+
+    /// Show what is being browsed and how it is filtered in a log's title
+    fn update_title(&mut self, container: &str) {
+        let Some(state) = self.state(container) else {
+            return;
+        };
+        let mut title = format!("Logs - {container}{}", describe_source(&state.source));
+        if let Some(history) = &state.history {
+            title.push_str(&describe_range(&history.range));
+        }
+        title.push_str(&state.filter.describe());
+        let Some(layer) = self.get_by_name(container) else {
+            return;
+        };
+        self.get(layer).get_inner_mut().get_mut().set_title(title);
+    }
+
+    /// Open a selector for which journal the shown container's log is read
+    /// from
+    pub fn open_source(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let selector = SelectView::new()
+            .item("Host service", Some(LogSource::Host))
+            .item("Guest, all units", Some(LogSource::Guest(None)))
+            .item("Guest, one unit...", None)
+            .on_submit({
+                let container = container.clone();
+                move |root, source: &Option<LogSource>| {
+                    root.pop_layer();
+                    match source {
+                        Some(source) => Self::set_source(root, &container, source.clone()),
+                        None => Self::open_guest_unit(root, &container),
+                    }
+                }
+            });
+        root.add_layer(
+            Dialog::around(selector)
+                .title(format!("Log source - {container}"))
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Open a prompt for the guest unit to show the log of
+    fn open_guest_unit(root: &mut Cursive, container: &str) {
+        let prompt = EditView::new()
+            .on_submit({
+                let container = container.to_string();
+                move |root, unit| {
+                    root.pop_layer();
+                    let unit = Some(unit.trim().to_string()).filter(|unit| !unit.is_empty());
+                    Self::set_source(root, &container, LogSource::Guest(unit));
+                }
+            })
+            .min_width(40);
+        root.add_layer(
+            Dialog::around(prompt)
+                .title(format!("Guest unit - {container}"))
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Switch which journal a container's live log is read from, unless it
+    /// was destroyed
+    fn set_source(root: &mut Cursive, container: &str, source: LogSource) {
+        let log = Main::get_self(root).get_container_log();
+        if log
+            .state(container)
+            .is_none_or(|state| state.source == source)
+        {
+            return;
+        }
+        log.stop_history(container);
+        let line_cap = log.line_cap;
+        let Some(state) = log.state(container) else {
+            return;
+        };
+        let view = match source {
+            LogSource::Host => state
+                .hidden
+                .remove(&LogSource::Host)
+                .expect("Host log should be kept while not shown"),
+            LogSource::Guest(_) => Self::new_view(line_cap, &state.filter),
+        };
+        let Some(previous) = log.replace_view(container, view) else {
+            return;
+        };
+        let Some(state) = log.state(container) else {
+            return;
+        };
+        // Keep following the host, but stop following the guest
+        if state.source == LogSource::Host {
+            state.hidden.insert(LogSource::Host, previous);
+        }
+        state.source = source.clone();
+        log.update_title(container);
+        crate::send_command(
+            root,
+            ContainerId::new(container),
+            Command::SetLogSource(source),
+        );
+    }
+
+    /// Open a prompt for browsing the shown container's past logs
+    pub fn open_history(root: &mut Cursive) {
+        let log = Main::get_self(root).get_container_log();
+        let Some(container) = log.shown_container() else {
+            return;
+        };
+        let Some(state) = log.state(&container) else {
+            return;
+        };
+        let range = state
+            .history
+            .as_ref()
+            .map(|history| history.range.clone())
+            .unwrap_or_default();
+        let field = |label, name: &str, value: Option<String>| {
+            LinearLayout::horizontal()
+                .child(TextView::new(label).fixed_width(7))
+                .child(
+                    EditView::new()
+                        .content(value.unwrap_or_default())
+                        .with_name(name)
+                        .min_width(30),
+                )
+        };
+        let form = LinearLayout::vertical()
+            .child(field("Since", HISTORY_SINCE, range.since))
+            .child(field("Until", HISTORY_UNTIL, range.until))
+            .child(field("Boot", HISTORY_BOOT, range.boot));
+        root.add_layer(
+            Dialog::around(form)
+                .title(format!("Log history - {container}"))
+                .button("Load", {
+                    let container = container.clone();
+                    move |root| {
+                        let value = |root: &mut Cursive, name| {
+                            let text = root
+                                .call_on_name(name, |edit: &mut EditView| edit.get_content())
+                                .expect("Log history field should be present");
+                            Some(text.trim().to_string()).filter(|text| !text.is_empty())
+                        };
+                        let range = LogRange {
+                            since: value(root, HISTORY_SINCE),
+                            until: value(root, HISTORY_UNTIL),
+                            boot: value(root, HISTORY_BOOT),
+                        };
+                        root.pop_layer();
+                        Self::browse_history(root, &container, range);
+                    }
+                })
+                .button("Live", move |root| {
+                    root.pop_layer();
+                    Main::get_self(root)
+                        .get_container_log()
+                        .stop_history(&container);
+                })
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Replace a container's log with its past logs within a range, unless it
+    /// was destroyed
+    fn browse_history(root: &mut Cursive, container: &str, range: LogRange) {
+        let log = Main::get_self(root).get_container_log();
+        let line_cap = log.line_cap;
+        let Some(state) = log.state(container) else {
+            return;
+        };
+        let view = Self::new_view(line_cap, &state.filter);
+        let Some(previous) = log.replace_view(container, view) else {
+            return;
+        };
+        let Some(state) = log.state(container) else {
+            return;
+        };
+        // Keep the live log aside, unless it already is from browsing before
+        if state.history.is_none() {
+            state.hidden.insert(state.source.clone(), previous);
+        }
+        state.history = Some(LogHistory {
+            range: range.clone(),
+            oldest: None,
+            loading: true,
+            complete: false,
+        });
+        let source = state.source.clone();
+        log.update_title(container);
+        crate::send_command(
+            root,
+            ContainerId::new(container),
+            Command::LoadLogHistory {
+                source,
+                range,
+                before: None,
+            },
+        );
+    }
+
+    /// Go back to following a container's live log
+    fn stop_history(&mut self, container: &str) {
+        let Some(state) = self.state(container) else {
+            return;
+        };
+        if state.history.take().is_none() {
+            return;
+        }
+        let live = state
+            .hidden
+            .remove(&state.source)
+            .expect("Live log should be kept while browsing history");
+        self.replace_view(container, live);
+        self.update_title(container);
+    }
+
+    /// Add a page of past logs read by the backend
+    pub fn add_history(&mut self, container: &ContainerId, page: LogPage) {
+        // Ignore pages for ranges no longer being browsed
+        let Some(state) = self.states.get_mut(container.name()) else {
+            return;
+        };
+        if state.source != page.source {
+            return;
+        }
+        let Some(history) = &mut state.history else {
+            return;
+        };
+        if history.range != page.range || history.oldest != page.before {
+            return;
+        }
+        history.loading = false;
+        history.complete = page.complete;
+        if let Some(oldest) = page.entries.first() {
+            history.oldest = Some(oldest.cursor.clone());
+        }
+        let Some(layer) = self.get_by_name(container.name()) else {
+            return;
+        };
+        let mut panel = self.get(layer).get_inner_mut().get_mut();
+        let scroll = panel.get_inner_mut();
+        let added = scroll.get_inner_mut().prepend(page.entries);
+        if page.before.is_none() {
+            // Start from the newest entries, paging back while scrolling up
+            scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+        } else {
+            // Keep the lines that were shown in place
+            let viewport = scroll.content_viewport();
+            scroll.set_scroll_strategy(ScrollStrategy::KeepRow);
+            scroll.set_offset((viewport.left(), viewport.top() + added));
+        }
     }
 
-    fn get(
+    /// Load older past logs once the top of a container's log is reached
+    fn load_older(root: &mut Cursive, container: &ContainerId) {
+        let log = Main::get_self(root).get_container_log();
+        let Some(state) = log.states.get_mut(container.name()) else {
+            return;
+        };
+        let Some(history) = &mut state.history else {
+            return;
+        };
+        if history.loading || history.complete {
+            return;
+        }
+        let Some(before) = history.oldest.clone() else {
+            return;
+        };
+        history.loading = true;
+        let range = history.range.clone();
+        let source = state.source.clone();
+        crate::send_command(
+            root,
+            container.clone(),
+            Command::LoadLogHistory {
+                source,
+                range,
+                before: Some(before),
+            },
+        );
+    }
+
+    /// Create an empty log view, with a container's filter applied
+    fn new_view(line_cap: usize, filter: &EntryFilter) -> LogView<JournalEntry> {
+        let mut view = LogView::new(line_cap);
+        view.set_filter(filter.log_filter());
+        view
+    }
+
+    /// Show another log view for a container, returning the one shown before,
+    /// or `None` if the container was destroyed
+    fn replace_view(
         &mut self,
-        pos: LayerPosition,
-    ) -> &mut HideableView<NamedView<Panel<ScrollView<LinearLayout>>>> {
+        container: &str,
+        view: LogView<JournalEntry>,
+    ) -> Option<LogView<JournalEntry>> {
+        let layer = self.get_by_name(container)?;
+        let mut panel = self.get(layer).get_inner_mut().get_mut();
+        let scroll = panel.get_inner_mut();
+        scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+        Some(mem::replace(scroll.get_inner_mut(), view))
+    }
+
+    /// Get what is shown of a container's log, or `None` if the container was
+    /// destroyed
+    fn state(&mut self, container: &str) -> Option<&mut LogState> {
+        self.states.get_mut(container)
+    }
+
+    /// Open a prompt for searching the shown container's log
+    ///
+    /// Matches are highlighted as the pattern is typed, jumping to the newest
+    /// one. An empty pattern clears the search.
+    pub fn open_search(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let prompt = EditView::new()
+            .on_edit({
+                let container = container.clone();
+                move |root, text, _| {
+                    // Keep the last valid search while a pattern is being typed
+                    if let Ok(search) = parse_search(text) {
+                        Main::get_self(root)
+                            .get_container_log()
+                            .search(&container, search);
+                    }
+                }
+            })
+            .on_submit({
+                let container = container.clone();
+                move |root, text| match parse_search(text) {
+                    Ok(search) => {
+                        root.pop_layer();
+                        Main::get_self(root)
+                            .get_container_log()
+                            .search(&container, search);
+                    }
+                    Err(error) => root.add_layer(Dialog::info(error.to_string())),
+                }
+            })
+            .min_width(40);
+        root.add_layer(
+            Dialog::around(prompt)
+                .title(format!("Search logs - {container} (regex)"))
+                .dismiss_button("Close"),
+        );
+    }
+
+    /// Jump to the next search match in the shown container's log
+    pub fn next_match(root: &mut Cursive) {
+        Main::get_self(root).get_container_log().select_match(true);
+    }
+
+    /// Jump to the previous search match in the shown container's log
+    pub fn previous_match(root: &mut Cursive) {
+        Main::get_self(root).get_container_log().select_match(false);
+    }
+
+    fn search(&mut self, container: &str, search: Option<Regex>) {
+        let Some(layer) = self.get_by_name(container) else {
+            return;
+        };
+        let mut panel = self.get(layer).get_inner_mut().get_mut();
+        let scroll = panel.get_inner_mut();
+        let searching = search.is_some();
+        scroll.get_inner_mut().set_search(search);
+        if searching && let Some(line) = scroll.get_inner_mut().select_match(false) {
+            Self::scroll_to(scroll, line);
+        }
+    }
+
+    fn select_match(&mut self, forward: bool) {
+        let Some(container) = self.shown_container() else {
+            return;
+        };
+        let Some(layer) = self.get_by_name(&container) else {
+            return;
+        };
+        let mut panel = self.get(layer).get_inner_mut().get_mut();
+        let scroll = panel.get_inner_mut();
+        if let Some(line) = scroll.get_inner_mut().select_match(forward) {
+            Self::scroll_to(scroll, line);
+        }
+    }
+
+    /// Scroll a log so that a line is in the middle of the view
+    fn scroll_to(scroll: &mut ScrollView<LogView<JournalEntry>>, line: usize) {
+        let viewport = scroll.content_viewport();
+        // Stop following new lines, which would scroll the match away
+        scroll.set_scroll_strategy(ScrollStrategy::KeepRow);
+        scroll.set_offset((viewport.left(), line.saturating_sub(viewport.height() / 2)));
+    }
+
+    /// Get the entries of the log shown for a container, optionally only those
+    /// passing its filter, or `None` if the container was destroyed
+    pub fn entries(&mut self, container: &str, filtered: bool) -> Option<Vec<JournalEntry>> {
+        let layer = self.get_by_name(container)?;
+        let panel = self.get(layer).get_inner_mut().get_mut();
+        let view = panel.get_inner().get_inner();
+        Some(if filtered {
+            view.shown_items().cloned().collect()
+        } else {
+            view.items().cloned().collect()
+        })
+    }
+
+    /// Get the name of the container whose log is shown, if any
+    pub fn shown_container(&mut self) -> Option<String> {
+        let layer = self
+            .inner
+            .get_inner_mut()
+            .get_mut(LayerPosition::FromFront(0))?
+            .downcast_mut::<ContainerLayer>()?;
+        Some(layer.get_inner().name().to_string())
+    }
+
+    /// Get the layer of a container's log, or `None` if the container was
+    /// destroyed
+    fn get_by_name(&mut self, container: &str) -> Option<LayerPosition> {
+        self.inner.get_inner_mut().find_layer_from_name(container)
+    }
+
+    fn merged_layer(&mut self) -> LayerPosition {
+        self.inner
+            .get_inner_mut()
+            .find_layer_from_name(MERGED_LOG)
+            .expect("Merged log should be present")
+    }
+
+    fn get(&mut self, pos: LayerPosition) -> &mut ContainerLayer {
         self.inner
             .get_inner_mut()
             .get_mut(pos)
@@ -65,8 +833,187 @@ impl ContainerLog {
             .downcast_mut()
             .expect("Child view should be expected type")
     }
+
+    fn get_merged(&mut self, pos: LayerPosition) -> &mut MergedLayer {
+        self.inner
+            .get_inner_mut()
+            .get_mut(pos)
+            .expect("Passed possition should be valid")
+            .downcast_mut()
+            .expect("Merged log should be expected type")
+    }
 }
 
 impl ViewWrapper for ContainerLog {
     cursive::wrap_impl!(self.inner: FocusTracker<StackView>);
 }
+
+impl LogLine for JournalEntry {
+    fn line(&self) -> StyledString {
+        let source = match (&self.identifier, self.pid) {
+            (Some(identifier), Some(pid)) => format!("{identifier}[{pid}]"),
+            (Some(identifier), None) => identifier.clone(),
+            (None, Some(pid)) => format!("[{pid}]"),
+            (None, None) => "-".to_string(),
+        };
+        let style = match self.priority {
+            Some(Priority::Emergency | Priority::Alert | Priority::Critical) => {
+                Style::from(Color::Light(BaseColor::Red)).combine(Effect::Bold)
+            }
+            Some(Priority::Error) => Style::from(Color::Light(BaseColor::Red)),
+            Some(Priority::Warning) => Style::from(Color::Light(BaseColor::Yellow)),
+            Some(Priority::Notice) => Style::from(Effect::Bold),
+            Some(Priority::Info) | None => Style::none(),
+            Some(Priority::Debug) => Style::from(Color::Light(BaseColor::Black)),
+        };
+        StyledString::styled(
+            format!("{} {source}: {}", timestamp(self.timestamp), self.message),
+            style,
+        )
+    }
+}
+
+/// Entry of the merged log, prefixed with the container it is from
+struct MergedEntry {
+    container: ContainerId,
+    colour: Color,
+    entry: JournalEntry,
+}
+
+impl LogLine for MergedEntry {
+    fn line(&self) -> StyledString {
+        let mut line = StyledString::styled(
+            format!("{} ", self.container),
+            Style::from(self.colour).combine(Effect::Bold),
+        );
+        line.append(self.entry.line());
+        line
+    }
+}
+
+/// Names of the log history prompt fields
+const HISTORY_SINCE: &str = "log-history-since";
+const HISTORY_UNTIL: &str = "log-history-until";
+const HISTORY_BOOT: &str = "log-history-boot";
+
+/// What is shown of a container's log
+#[derive(Default)]
+struct LogState {
+    filter: EntryFilter,
+    /// Journal the live log is read from
+    source: LogSource,
+    /// Live logs kept up to date while not shown
+    hidden: HashMap<LogSource, LogView<JournalEntry>>,
+    /// Past logs browsed instead of the live log
+    history: Option<LogHistory>,
+}
+
+/// State of browsing a container's past logs
+struct LogHistory {
+    range: LogRange,
+    /// Cursor of the oldest entry loaded
+    oldest: Option<String>,
+    /// Whether a page is being read by the backend
+    loading: bool,
+    /// Whether all entries in the range have been loaded
+    complete: bool,
+}
+
+/// Describe the source of a live log for a log title
+fn describe_source(source: &LogSource) -> String {
+    match source {
+        LogSource::Host => String::new(),
+        LogSource::Guest(None) => " [guest]".to_string(),
+        LogSource::Guest(Some(unit)) => format!(" [guest {unit}]"),
+    }
+}
+
+/// Describe a range of past logs for a log title
+fn describe_range(range: &LogRange) -> String {
+    let mut description = " [history".to_string();
+    if let Some(since) = &range.since {
+        description.push_str(&format!(" since {since}"));
+    }
+    if let Some(until) = &range.until {
+        description.push_str(&format!(" until {until}"));
+    }
+    if let Some(boot) = &range.boot {
+        description.push_str(&format!(" boot {boot}"));
+    }
+    description.push(']');
+    description
+}
+
+/// Which entries of a container's log are shown
+#[derive(Clone)]
+struct EntryFilter {
+    /// `FIELD=value` terms which must all match exactly
+    fields: Vec<(String, String)>,
+    /// Least severe priority shown
+    threshold: Priority,
+}
+
+impl Default for EntryFilter {
+    fn default() -> Self {
+        Self {
+            fields: Vec::new(),
+            threshold: Priority::Debug,
+        }
+    }
+}
+
+impl EntryFilter {
+    fn matches(&self, entry: &JournalEntry) -> bool {
+        // Entries without a priority are treated as informational
+        entry.priority.unwrap_or(Priority::Info) <= self.threshold
+            && self
+                .fields
+                .iter()
+                .all(|(field, value)| entry.fields.get(field) == Some(value))
+    }
+
+    /// Get the filter for a log view, or `None` if it shows every entry
+    fn log_filter(&self) -> Option<LogFilter<JournalEntry>> {
+        if self.fields.is_empty() && self.threshold == Priority::Debug {
+            return None;
+        }
+        let filter = self.clone();
+        Some(Box::new(move |entry| filter.matches(entry)))
+    }
+
+    /// Describe the filter for a log title
+    fn describe(&self) -> String {
+        let mut description = String::new();
+        if self.threshold != Priority::Debug {
+            description.push_str(&format!(" [{} and above]", self.threshold.name()));
+        }
+        if !self.fields.is_empty() {
+            let fields = self
+                .fields
+                .iter()
+                .map(|(field, value)| format!("{field}={value}"))
+                .collect::<Vec<_>>();
+            description.push_str(&format!(" [{}]", fields.join(" ")));
+        }
+        description
+    }
+}
+
+/// Parse a search pattern, with an empty pattern meaning no search
+fn parse_search(text: &str) -> Result<Option<Regex>, regex::Error> {
+    if text.is_empty() {
+        return Ok(None);
+    }
+    Regex::new(text).map(Some)
+}
+
+/// Parse space-separated `FIELD=value` filter terms
+fn parse_field_filter(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.split_whitespace()
+        .map(|term| {
+            term.split_once('=')
+                .map(|(field, value)| (field.to_string(), value.to_string()))
+                .ok_or_else(|| format!("Filter term {term} should be of the form FIELD=value"))
+        })
+        .collect()
+}
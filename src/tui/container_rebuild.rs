@@ -0,0 +1,178 @@
+use super::{LogView, Main};
+use crate::backend::messages::{Command, ContainerId, ContainerSource, UnitOperation};
+use anyhow::Error;
+use cursive::Cursive;
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
+use cursive::view::{Nameable, Resizable, ScrollStrategy, ViewWrapper};
+use cursive::views::{
+    Dialog, EditView, LinearLayout, RadioGroup, ResizedView, ScrollView, TextView,
+};
+use std::path::PathBuf;
+
+/// Pane showing the output of rebuilding a container, offering to restart
+/// it once the build succeeds
+pub struct ContainerRebuild {
+    inner: Dialog,
+    container: ContainerId,
+}
+
+/// Which configuration a container is rebuilt from
+#[derive(Clone, Copy)]
+enum SourceChoice {
+    /// The configuration it was last built from
+    Current,
+    Flake,
+    ConfigFile,
+}
+
+/// How many lines of build output are kept
+const LINE_CAP: usize = 5000;
+
+impl ContainerRebuild {
+    /// Open a prompt for rebuilding the shown container
+    pub fn open(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let container = ContainerId::new(&container);
+        if root
+            .find_name::<Self>(&Self::view_name(&container))
+            .is_some()
+        {
+            root.add_layer(Dialog::info(format!(
+                "Close the build of {container} before rebuilding it again"
+            )));
+            return;
+        }
+        let mut source = RadioGroup::new();
+        let form = LinearLayout::vertical()
+            .child(source.button(SourceChoice::Current, "Current configuration"))
+            .child(source.button(SourceChoice::Flake, "Flake"))
+            .child(source.button(SourceChoice::ConfigFile, "Config file"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Flake or file ").fixed_width(14))
+                    .child(EditView::new().with_name(REBUILD_SOURCE).min_width(40)),
+            );
+        root.add_layer(
+            Dialog::around(form)
+                .title(format!("Rebuild container - {container}"))
+                .button("Rebuild", move |root| {
+                    Self::start(root, &container, *source.selection())
+                })
+                .dismiss_button("Cancel"),
+        );
+    }
+
+    /// Show the build output of a container, if its pane is open
+    pub fn output(root: &mut Cursive, container: &ContainerId, line: String) {
+        root.call_on_name(&Self::view_name(container), |rebuild: &mut Self| {
+            let scroll = rebuild.get_output();
+            let follow = scroll.is_at_bottom();
+            scroll.get_inner_mut().push(line);
+            if follow {
+                scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+            }
+        });
+    }
+
+    /// Show that rebuilding a container finished, offering to restart it if
+    /// the build succeeded
+    pub fn finish(root: &mut Cursive, container: &ContainerId, error: Option<&Error>) {
+        root.call_on_name(&Self::view_name(container), |rebuild: &mut Self| {
+            let status = match error {
+                None => StyledString::styled(
+                    "Build succeeded, restart the container to switch to it",
+                    Style::from(Color::Light(BaseColor::Green)).combine(Effect::Bold),
+                ),
+                Some(error) => StyledString::styled(
+                    format!("{error:#}"),
+                    Style::from(Color::Light(BaseColor::Red)).combine(Effect::Bold),
+                ),
+            };
+            rebuild.get_status().set_content(status);
+            if error.is_none() {
+                let container = rebuild.container.clone();
+                rebuild.inner.add_button("Restart", move |root| {
+                    root.pop_layer();
+                    crate::send_command(
+                        root,
+                        container.clone(),
+                        Command::Unit(UnitOperation::Restart),
+                    );
+                });
+            }
+        });
+    }
+
+    /// Rebuild a container from the configuration chosen in the prompt,
+    /// replacing it with the build output pane
+    fn start(root: &mut Cursive, container: &ContainerId, choice: SourceChoice) {
+        let text = root
+            .call_on_name(REBUILD_SOURCE, |edit: &mut EditView| edit.get_content())
+            .expect("Rebuild source field should be present")
+            .trim()
+            .to_string();
+        let source = match choice {
+            SourceChoice::Current => None,
+            SourceChoice::Flake => Some(ContainerSource::Flake(text.clone())),
+            SourceChoice::ConfigFile => Some(ContainerSource::ConfigFile(PathBuf::from(&text))),
+        };
+        if source.is_some() && text.is_empty() {
+            root.add_layer(Dialog::info("Enter a flake reference or config file"));
+            return;
+        }
+        root.pop_layer();
+        let output = LinearLayout::vertical()
+            .child(TextView::new("Building..."))
+            .child(
+                ScrollView::new(LogView::<String>::new(LINE_CAP))
+                    .scroll_x(true)
+                    .min_size((100, 25)),
+            );
+        let rebuild = Self {
+            inner: Dialog::around(output)
+                .title(format!("Rebuild - {container}"))
+                .dismiss_button("Close"),
+            container: container.clone(),
+        };
+        root.add_layer(rebuild.with_name(Self::view_name(container)));
+        crate::send_command(root, container.clone(), Command::Rebuild(source));
+    }
+
+    fn get_status(&mut self) -> &mut TextView {
+        self.get_layout()
+            .get_child_mut(0)
+            .expect("Build status should be present")
+            .downcast_mut::<TextView>()
+            .expect("Build status should be expected type")
+    }
+
+    fn get_output(&mut self) -> &mut ScrollView<LogView<String>> {
+        self.get_layout()
+            .get_child_mut(1)
+            .expect("Build output should be present")
+            .downcast_mut::<ResizedView<ScrollView<LogView<String>>>>()
+            .expect("Build output should be expected type")
+            .get_inner_mut()
+    }
+
+    fn get_layout(&mut self) -> &mut LinearLayout {
+        self.inner
+            .get_content_mut()
+            .downcast_mut::<LinearLayout>()
+            .expect("Build pane should be expected type")
+    }
+
+    fn view_name(container: &ContainerId) -> String {
+        format!("rebuild-{container}")
+    }
+}
+
+impl ViewWrapper for ContainerRebuild {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+/// Name of the rebuild prompt's flake or config file field
+const REBUILD_SOURCE: &str = "container-rebuild-source";
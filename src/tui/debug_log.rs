@@ -1,31 +1,42 @@
+use super::LogView;
+use crate::backend::messages::ContainerId;
 use anyhow::Error;
-use cursive::view::ViewWrapper;
-use cursive::views::{LinearLayout, Panel, TextView};
+use cursive::view::{ScrollStrategy, ViewWrapper};
+use cursive::views::{Panel, ScrollView};
+
+/// How many lines of internal logs are kept
+const LINE_CAP: usize = 1000;
 
 pub struct DebugLog {
-    inner: Panel<LinearLayout>,
+    inner: Panel<ScrollView<LogView<String>>>,
 }
 
 impl DebugLog {
     pub fn new() -> Self {
         Self {
-            inner: Panel::new(LinearLayout::vertical()).title("Internal Logs"),
+            inner: Panel::new(ScrollView::new(LogView::new(LINE_CAP)).scroll_x(true))
+                .title("Internal Logs"),
         }
     }
 
-    pub fn log(&mut self, container: &str, log: &str) {
+    pub fn log(&mut self, container: &ContainerId, log: &str) {
         self.add(format!("[LOG] {container} - {log}"));
     }
 
-    pub fn error(&mut self, container: &str, error: Error) {
+    pub fn error(&mut self, container: &ContainerId, error: Error) {
         self.add(format!("[ERROR] {container} - {error:#}"));
     }
 
     fn add(&mut self, line: String) {
-        self.inner.get_inner_mut().add_child(TextView::new(line));
+        let scroll = self.inner.get_inner_mut();
+        let follow = scroll.is_at_bottom();
+        scroll.get_inner_mut().push(line);
+        if follow {
+            scroll.set_scroll_strategy(ScrollStrategy::StickToBottom);
+        }
     }
 }
 
 impl ViewWrapper for DebugLog {
-    cursive::wrap_impl!(self.inner: Panel<LinearLayout>);
+    cursive::wrap_impl!(self.inner: Panel<ScrollView<LogView<String>>>);
 }
@@ -0,0 +1,146 @@
+use super::utils::parse_timestamp;
+use super::{LogLine, Main};
+use crate::backend::messages::{Command, ContainerId, JournalEntry};
+use cursive::Cursive;
+use cursive::view::{Nameable, Resizable, ViewWrapper};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, RadioGroup, TextView};
+use std::path::PathBuf;
+
+/// Dialog for exporting the shown container's log to a file
+pub struct LogExport {
+    inner: Dialog,
+}
+
+/// Format of exported log entries
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    /// Lines as shown in the log
+    Text,
+    /// One JSON object of all journal fields per line, like
+    /// `journalctl --output json`
+    JsonLines,
+}
+
+impl LogExport {
+    /// Open the export dialog for the shown container's log
+    pub fn open(root: &mut Cursive) {
+        let Some(container) = Main::get_self(root).get_container_log().shown_container() else {
+            return;
+        };
+        let mut format = RadioGroup::new();
+        let field = |label, name, content: String| {
+            LinearLayout::horizontal()
+                .child(TextView::new(label).fixed_width(7))
+                .child(
+                    EditView::new()
+                        .content(content)
+                        .with_name(name)
+                        .min_width(40),
+                )
+        };
+        let form = LinearLayout::vertical()
+            .child(field("Path", EXPORT_PATH, format!("{container}.log")))
+            .child(field("Since", EXPORT_SINCE, String::new()))
+            .child(field("Until", EXPORT_UNTIL, String::new()))
+            .child(TextView::new("Times are UTC, as YYYY-MM-DD [HH:MM[:SS]]"))
+            .child(format.button(ExportFormat::Text, "Plain text"))
+            .child(format.button(ExportFormat::JsonLines, "JSON lines"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().checked().with_name(EXPORT_FILTERED))
+                    .child(TextView::new(" Only entries passing the log's filter")),
+            );
+        let export = Self {
+            inner: Dialog::around(form)
+                .title(format!("Export logs - {container}"))
+                .button("Export", move |root| {
+                    Self::export(root, &container, *format.selection())
+                })
+                .dismiss_button("Cancel"),
+        };
+        root.add_layer(export);
+    }
+
+    /// Export the entries chosen in the dialog, reporting invalid choices
+    fn export(root: &mut Cursive, container: &str, format: ExportFormat) {
+        let text = |root: &mut Cursive, name| {
+            root.call_on_name(name, |edit: &mut EditView| edit.get_content())
+                .expect("Export field should be present")
+                .trim()
+                .to_string()
+        };
+        let bound = |root: &mut Cursive, name| {
+            let text = text(root, name);
+            if text.is_empty() {
+                return Ok(None);
+            }
+            parse_timestamp(&text)
+                .map(Some)
+                .ok_or(format!("Invalid time {text}"))
+        };
+        let (since, until) = match (bound(root, EXPORT_SINCE), bound(root, EXPORT_UNTIL)) {
+            (Ok(since), Ok(until)) => (since, until),
+            (Err(error), _) | (_, Err(error)) => {
+                root.add_layer(Dialog::info(error));
+                return;
+            }
+        };
+        let path = text(root, EXPORT_PATH);
+        if path.is_empty() {
+            root.add_layer(Dialog::info("Enter a path to export to"));
+            return;
+        }
+        let filtered = root
+            .call_on_name(EXPORT_FILTERED, |checkbox: &mut Checkbox| {
+                checkbox.is_checked()
+            })
+            .expect("Export filter checkbox should be present");
+        // The container may have been destroyed while exporting was set up
+        let Some(entries) = Main::get_self(root)
+            .get_container_log()
+            .entries(container, filtered)
+        else {
+            root.pop_layer();
+            return;
+        };
+        let entries = entries
+            .into_iter()
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .filter(|entry| until.is_none_or(|until| entry.timestamp <= until))
+            .collect::<Vec<_>>();
+        let contents = entries
+            .iter()
+            .map(|entry| format_entry(entry, format) + "\n")
+            .collect();
+        root.pop_layer();
+        crate::send_command(
+            root,
+            ContainerId::new(container),
+            Command::ExportLog {
+                path: PathBuf::from(path),
+                contents,
+                count: entries.len(),
+            },
+        );
+    }
+}
+
+impl ViewWrapper for LogExport {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+/// Names of the export dialog fields
+const EXPORT_PATH: &str = "log-export-path";
+const EXPORT_SINCE: &str = "log-export-since";
+const EXPORT_UNTIL: &str = "log-export-until";
+const EXPORT_FILTERED: &str = "log-export-filtered";
+
+/// Format an exported log entry as a line, without the trailing newline
+fn format_entry(entry: &JournalEntry, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Text => entry.line().source().to_string(),
+        ExportFormat::JsonLines => {
+            serde_json::to_string(&entry.fields).expect("Journal fields should serialize")
+        }
+    }
+}
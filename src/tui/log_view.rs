@@ -0,0 +1,241 @@
+use cursive::theme::{BaseColor, Color, ColorStyle, Effect, Style};
+use cursive::utils::markup::StyledString;
+use cursive::{Printer, Vec2, View};
+use regex::Regex;
+use std::collections::VecDeque;
+
+/// An item that can be shown as a line of a [`LogView`]
+pub trait LogLine: Send + Sync + 'static {
+    fn line(&self) -> StyledString;
+}
+
+impl LogLine for String {
+    fn line(&self) -> StyledString {
+        StyledString::plain(self)
+    }
+}
+
+/// Filter deciding which items of a [`LogView`] are shown
+pub type LogFilter<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Log of items keeping at most a fixed number of the newest ones
+///
+/// Only the lines visible through the enclosing scroll view are drawn, so
+/// redrawing stays cheap however many items are kept. Items hidden by the
+/// filter are kept, and shown again if the filter changes. Text matching the
+/// search is highlighted, with the selected match standing out.
+pub struct LogView<T> {
+    items: VecDeque<T>,
+    cap: usize,
+    width: usize,
+    filter: Option<LogFilter<T>>,
+    /// Number of items passing the filter
+    shown: usize,
+    /// Number of items dropped from the front since the log was created
+    dropped: usize,
+    search: Option<Regex>,
+    /// Index of the item holding the selected match, counting dropped items
+    selected: Option<usize>,
+}
+
+impl<T: LogLine> LogView<T> {
+    /// Create an empty log keeping at most `cap` items
+    pub fn new(cap: usize) -> Self {
+        Self {
+            items: VecDeque::new(),
+            cap,
+            width: 0,
+            filter: None,
+            shown: 0,
+            dropped: 0,
+            search: None,
+            selected: None,
+        }
+    }
+
+    /// Add an item, dropping the oldest one if the log is full
+    pub fn push(&mut self, item: T) {
+        self.width = self.width.max(item.line().width());
+        if self.is_shown(&item) {
+            self.shown += 1;
+        }
+        self.items.push_back(item);
+        while self.items.len() > self.cap {
+            let item = self.items.pop_front().expect("Log should not be empty");
+            if self.is_shown(&item) {
+                self.shown -= 1;
+            }
+            self.dropped += 1;
+        }
+    }
+
+    /// Add an item after the last one ordered before or with it by `key`,
+    /// dropping the oldest item if the log is full
+    pub fn insert_by_key<K: Ord>(&mut self, item: T, key: impl Fn(&T) -> K) {
+        let item_key = key(&item);
+        let index = self
+            .items
+            .partition_point(|existing| key(existing) <= item_key);
+        if index == self.items.len() {
+            self.push(item);
+            return;
+        }
+        self.width = self.width.max(item.line().width());
+        if self.is_shown(&item) {
+            self.shown += 1;
+        }
+        self.items.insert(index, item);
+        self.selected = self.selected.map(|selected| {
+            if selected >= self.dropped + index {
+                selected + 1
+            } else {
+                selected
+            }
+        });
+        while self.items.len() > self.cap {
+            let item = self.items.pop_front().expect("Log should not be empty");
+            if self.is_shown(&item) {
+                self.shown -= 1;
+            }
+            self.dropped += 1;
+        }
+    }
+
+    /// Iterate over the kept items, from oldest to newest
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
+    /// Iterate over the kept items passing the filter, from oldest to newest
+    pub fn shown_items(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().filter(|item| self.is_shown(item))
+    }
+
+    /// Add older items before the existing ones, dropping the newest ones if
+    /// the log is full
+    ///
+    /// Returns how many lines the added items take up.
+    pub fn prepend(&mut self, items: Vec<T>) -> usize {
+        let count = items.len();
+        let mut added = 0;
+        for item in items.into_iter().rev() {
+            self.width = self.width.max(item.line().width());
+            if self.is_shown(&item) {
+                added += 1;
+            }
+            self.items.push_front(item);
+        }
+        self.shown += added;
+        self.selected = self.selected.map(|selected| selected + count);
+        while self.items.len() > self.cap {
+            let item = self.items.pop_back().expect("Log should not be empty");
+            if self.is_shown(&item) {
+                self.shown -= 1;
+            }
+        }
+        added
+    }
+
+    /// Set which items are shown, or show all of them if `None`
+    pub fn set_filter(&mut self, filter: Option<LogFilter<T>>) {
+        self.filter = filter;
+        self.shown = self.items.iter().filter(|item| self.is_shown(item)).count();
+    }
+
+    /// Highlight text matching `search`, or stop highlighting if `None`
+    ///
+    /// This also clears the selected match.
+    pub fn set_search(&mut self, search: Option<Regex>) {
+        self.search = search;
+        self.selected = None;
+    }
+
+    /// Select the next shown match after the selected one, or the one before
+    /// it if `forward` is false, wrapping around at either end
+    ///
+    /// Returns the line of the newly selected match, if there is one.
+    pub fn select_match(&mut self, forward: bool) -> Option<usize> {
+        let search = self.search.as_ref()?;
+        let matches = self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| self.is_shown(item))
+            .enumerate()
+            .filter(|(_, (_, item))| search.is_match(item.line().source()))
+            .map(|(line, (index, _))| (line, self.dropped + index));
+        let (line, index) = match (forward, self.selected) {
+            (true, Some(selected)) => {
+                let mut matches = matches.peekable();
+                let first = *matches.peek()?;
+                matches
+                    .find(|(_, index)| *index > selected)
+                    .unwrap_or(first)
+            }
+            (true, None) => matches.min()?,
+            (false, selected) => {
+                let matches = matches.collect::<Vec<_>>();
+                let last = *matches.last()?;
+                selected
+                    .and_then(|selected| {
+                        matches
+                            .into_iter()
+                            .rev()
+                            .find(|(_, index)| *index < selected)
+                    })
+                    .unwrap_or(last)
+            }
+        };
+        self.selected = Some(index);
+        Some(line)
+    }
+
+    fn is_shown(&self, item: &T) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(item))
+    }
+
+    /// Draw highlights over the search matches of a line
+    fn highlight(&self, printer: &Printer, y: usize, line: &StyledString, selected: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let style = if selected {
+            Style::from(ColorStyle::new(
+                Color::Dark(BaseColor::Black),
+                Color::Light(BaseColor::Yellow),
+            ))
+            .combine(Effect::Bold)
+        } else {
+            Style::from(Effect::Reverse)
+        };
+        let text = line.source();
+        for found in search.find_iter(text) {
+            let x = StyledString::plain(&text[..found.start()]).width();
+            printer.with_style(style, |printer| printer.print((x, y), found.as_str()));
+        }
+    }
+}
+
+impl<T: LogLine> View for LogView<T> {
+    fn draw(&self, printer: &Printer) {
+        let start = printer.content_offset.y;
+        for (y, (index, item)) in self
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| self.is_shown(item))
+            .enumerate()
+            .skip(start)
+            .take(printer.output_size.y)
+        {
+            let line = item.line();
+            printer.print_styled((0, y), &line);
+            let selected = self.selected == Some(self.dropped + index);
+            self.highlight(printer, y, &line, selected);
+        }
+    }
+
+    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
+        Vec2::new(self.width, self.shown)
+    }
+}
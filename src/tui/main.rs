@@ -1,7 +1,13 @@
-use super::{ContainerList, ContainerLog, DebugLog};
+use super::{
+    ContainerCreate, ContainerDetails, ContainerList, ContainerLog, ContainerRebuild, DebugLog,
+    LogExport,
+};
+use crate::backend::messages::Container;
 use cursive::Cursive;
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
 use cursive::view::ViewWrapper;
-use cursive::views::{LayerPosition, LinearLayout};
+use cursive::views::{LayerPosition, LinearLayout, TextView};
 
 /// Wrapper around the entire TUI
 pub struct Main {
@@ -9,16 +15,27 @@ pub struct Main {
 }
 
 impl Main {
-    /// Create the TUI
-    pub fn create(root: &mut Cursive, containers: &Vec<&'static str>) {
+    /// Create the TUI, keeping at most `log_line_cap` log lines per container
+    pub fn create(root: &mut Cursive, containers: &Vec<Container>, log_line_cap: usize) {
         root.add_global_callback('q', |s| s.quit());
-        root.add_layer(Self::new(containers));
+        root.add_global_callback('f', ContainerLog::open_filter);
+        root.add_global_callback('p', ContainerLog::open_priority_threshold);
+        root.add_global_callback('/', ContainerLog::open_search);
+        root.add_global_callback('n', ContainerLog::next_match);
+        root.add_global_callback('N', ContainerLog::previous_match);
+        root.add_global_callback('h', ContainerLog::open_history);
+        root.add_global_callback('g', ContainerLog::open_source);
+        root.add_global_callback('m', ContainerLog::open_merge);
+        root.add_global_callback('e', LogExport::open);
+        root.add_global_callback('c', ContainerCreate::open);
+        root.add_global_callback('u', ContainerRebuild::open);
+        root.add_layer(Self::new(containers, log_line_cap));
     }
 
     /// Get the main TUI wrapper from the cursive root
     pub fn get_self(root: &mut Cursive) -> &mut Self {
         root.screen_mut()
-            .get_mut(LayerPosition::FromFront(0))
+            .get_mut(LayerPosition::FromBack(0))
             .expect("Main view should be present")
             .downcast_mut::<Self>()
             .expect("Main view should be expected type")
@@ -32,15 +49,35 @@ impl Main {
             .expect("Debug log view should be expected type")
     }
 
+    /// Show whether the backend is connected to systemd
+    pub fn set_connected(&mut self, connected: bool) {
+        let status = self
+            .get_sidebar()
+            .get_child_mut(0)
+            .expect("Connection status view should be present")
+            .downcast_mut::<TextView>()
+            .expect("Connection status view should be expected type");
+        status.set_content(connection_status(connected));
+    }
+
     /// Get the container list
     pub fn get_container_list(&mut self) -> &mut ContainerList {
-        self.inner
+        self.get_sidebar()
             .get_child_mut(1)
             .expect("Container list view should be present")
             .downcast_mut::<ContainerList>()
             .expect("Container list view should be expected type")
     }
 
+    /// Get the details of the selected container
+    pub fn get_container_details(&mut self) -> &mut ContainerDetails {
+        self.get_sidebar()
+            .get_child_mut(2)
+            .expect("Container details view should be present")
+            .downcast_mut::<ContainerDetails>()
+            .expect("Container details view should be expected type")
+    }
+
     pub fn get_container_log(&mut self) -> &mut ContainerLog {
         self.inner
             .get_child_mut(2)
@@ -50,18 +87,45 @@ impl Main {
     }
 
     /// Create the TUI with a given list of containers
-    fn new(containers: &Vec<&'static str>) -> Self {
+    fn new(containers: &Vec<Container>, log_line_cap: usize) -> Self {
         let debug_log = DebugLog::new();
         let container_list = ContainerList::new(containers);
-        let container_log = ContainerLog::new(containers);
+        let container_details = ContainerDetails::new(containers);
+        let container_log = ContainerLog::new(containers, log_line_cap);
+        let sidebar = LinearLayout::vertical()
+            .child(TextView::new(connection_status(true)))
+            .child(container_list)
+            .child(container_details);
         let inner = LinearLayout::horizontal()
             .child(debug_log)
-            .child(container_list)
+            .child(sidebar)
             .child(container_log);
         Self { inner }
     }
+
+    /// Get the column holding the connection status, container list and
+    /// details
+    fn get_sidebar(&mut self) -> &mut LinearLayout {
+        self.inner
+            .get_child_mut(1)
+            .expect("Sidebar view should be present")
+            .downcast_mut::<LinearLayout>()
+            .expect("Sidebar view should be expected type")
+    }
 }
 
 impl ViewWrapper for Main {
     cursive::wrap_impl!(self.inner: LinearLayout);
 }
+
+/// Describe whether the backend is connected to systemd
+fn connection_status(connected: bool) -> StyledString {
+    if connected {
+        StyledString::plain(" systemd: connected")
+    } else {
+        StyledString::styled(
+            " systemd: DISCONNECTED",
+            Style::from(Color::Light(BaseColor::Red)).combine(Effect::Bold),
+        )
+    }
+}
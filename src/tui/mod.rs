@@ -1,8 +1,17 @@
 pub use container_controls::ContainerControls;
+pub use container_create::ContainerCreate;
+pub use container_destroy::ContainerDestroy;
+pub use container_details::ContainerDetails;
 pub use container_list::ContainerList;
 pub use container_log::ContainerLog;
+pub use container_rebuild::ContainerRebuild;
 pub use debug_log::DebugLog;
+pub use log_export::LogExport;
+pub use log_view::{LogFilter, LogLine, LogView};
 pub use main::Main;
+pub use resource_graphs::ResourceGraphs;
+pub use sparkline::Sparkline;
+pub use unit_details::UnitDetails;
 
 /// The root TUI wrapper
 mod main;
@@ -16,8 +25,39 @@ mod container_list;
 /// The controls for a container
 mod container_controls;
 
+/// Config details of the selected container
+mod container_details;
+
 /// Log viewer for container services
 mod container_log;
 
+/// Systemd unit properties of a container
+mod unit_details;
+
+/// Graphs of the recent resource usage of a container
+mod resource_graphs;
+
+/// Bar graph view for resource usage history
+mod sparkline;
+
+/// Bounded log viewer drawing only visible lines
+mod log_view;
+
+/// Export of container logs to files
+mod log_export;
+
+/// Creation of new containers
+mod container_create;
+
+/// Confirmation of destroying containers
+mod container_destroy;
+
+/// Rebuilding containers and showing the build output
+mod container_rebuild;
+
 /// TUI helper functions
 mod utils;
+
+/// Tests of the TUI drawn on cursive's puppet backend
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,88 @@
+use super::utils::bytes;
+use super::{Main, Sparkline};
+use crate::backend::messages::{ContainerId, ResourceHistory};
+use cursive::Cursive;
+use cursive::view::{Nameable, ViewWrapper};
+use cursive::views::{Dialog, LinearLayout, TextView};
+
+/// Dialog graphing the recent resource usage of a container
+pub struct ResourceGraphs {
+    inner: Dialog,
+}
+
+impl ResourceGraphs {
+    /// Open the resource graphs of a container on top of the TUI
+    pub fn open(root: &mut Cursive, container: &ContainerId) {
+        let content = LinearLayout::vertical()
+            .child(TextView::new("Memory"))
+            .child(Sparkline::new(60, 8))
+            .child(TextView::new("CPU"))
+            .child(Sparkline::new(60, 8));
+        let mut graphs = Self {
+            inner: Dialog::around(content)
+                .title(format!("Resource usage - {container}"))
+                .dismiss_button("Close"),
+        };
+        if let Some(controls) = Main::get_self(root)
+            .get_container_list()
+            .get_container(container)
+        {
+            graphs.update(controls.get_history());
+        }
+        root.add_layer(graphs.with_name(Self::view_name(container)));
+    }
+
+    /// Update the resource graphs of a container, if they are open
+    pub fn refresh(root: &mut Cursive, container: &ContainerId, history: &ResourceHistory) {
+        root.call_on_name(&Self::view_name(container), |graphs: &mut Self| {
+            graphs.update(history)
+        });
+    }
+
+    fn update(&mut self, history: &ResourceHistory) {
+        let content = self
+            .inner
+            .get_content_mut()
+            .downcast_mut::<LinearLayout>()
+            .expect("Resource graphs content should be expected type");
+        let memory = get_graph(content, 1);
+        memory.set_values(
+            history
+                .memory
+                .iter()
+                .map(|memory| memory.map(|memory| memory as f64)),
+        );
+        let memory_peak = bytes(memory.peak().map(|peak| peak as u64));
+        let cpu = get_graph(content, 3);
+        cpu.set_values(history.cpu.iter().copied());
+        let cpu_peak = cpu
+            .peak()
+            .map_or_else(|| "-".to_string(), |peak| format!("{:.1}%", peak * 100.0));
+        get_label(content, 0).set_content(format!("Memory (peak {memory_peak})"));
+        get_label(content, 2).set_content(format!("CPU (peak {cpu_peak})"));
+    }
+
+    fn view_name(container: &ContainerId) -> String {
+        format!("resource-graphs-{container}")
+    }
+}
+
+impl ViewWrapper for ResourceGraphs {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
+
+fn get_label(content: &mut LinearLayout, index: usize) -> &mut TextView {
+    content
+        .get_child_mut(index)
+        .expect("Resource graph label should be present")
+        .downcast_mut::<TextView>()
+        .expect("Resource graph label should be expected type")
+}
+
+fn get_graph(content: &mut LinearLayout, index: usize) -> &mut Sparkline {
+    content
+        .get_child_mut(index)
+        .expect("Resource graph should be present")
+        .downcast_mut::<Sparkline>()
+        .expect("Resource graph should be expected type")
+}
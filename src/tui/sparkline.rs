@@ -0,0 +1,64 @@
+use cursive::{Printer, Vec2, View};
+
+/// Block characters for bars filled to each eighth of a cell
+const BLOCKS: [&str; 8] = ["▁", "▂", "▃", "▄", "▅", "▆", "▇", "█"];
+
+/// Bar graph of recent values, drawn with block characters
+///
+/// The newest value is drawn on the right, and missing values are left blank
+pub struct Sparkline {
+    values: Vec<Option<f64>>,
+    size: Vec2,
+}
+
+impl Sparkline {
+    /// Create an empty sparkline of a fixed size
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            values: Vec::new(),
+            size: Vec2::new(width, height),
+        }
+    }
+
+    /// Replace the graphed values, oldest first
+    pub fn set_values(&mut self, values: impl IntoIterator<Item = Option<f64>>) {
+        self.values = values.into_iter().collect();
+    }
+
+    /// Get the largest of the shown values
+    pub fn peak(&self) -> Option<f64> {
+        self.shown().iter().flatten().copied().reduce(f64::max)
+    }
+
+    /// Get the values that fit in the sparkline
+    fn shown(&self) -> &[Option<f64>] {
+        &self.values[self.values.len().saturating_sub(self.size.x)..]
+    }
+}
+
+impl View for Sparkline {
+    fn draw(&self, printer: &Printer) {
+        let Some(peak) = self.peak().filter(|peak| *peak > 0.0) else {
+            return;
+        };
+        let shown = self.shown();
+        let offset = self.size.x - shown.len();
+        let height = self.size.y;
+        for (x, value) in shown.iter().enumerate() {
+            let Some(value) = value else {
+                continue;
+            };
+            let eighths = ((value / peak).clamp(0.0, 1.0) * (height * 8) as f64).round() as usize;
+            for row in 0..height {
+                let level = eighths.saturating_sub(row * 8).min(8);
+                if level > 0 {
+                    printer.print((offset + x, height - 1 - row), BLOCKS[level - 1]);
+                }
+            }
+        }
+    }
+
+    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
+        self.size
+    }
+}
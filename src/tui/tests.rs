@@ -0,0 +1,464 @@
+use super::utils::{parse_timestamp, timestamp};
+use super::{ContainerDestroy, ContainerRebuild, LogView, Main};
+use crate::backend::CommandSender;
+use crate::backend::messages::{
+    Command, Container, ContainerId, ContainerState, JobResult, JournalEntry, LogSource,
+    NamedCommand, NamedUpdate, OperationOutcome, Priority, UnitOperation, Update,
+};
+use crate::{DEFAULT_LOG_LINES, log_line_cap};
+use anyhow::anyhow;
+use cursive::backends::puppet::Backend;
+use cursive::backends::puppet::observed::ObservedScreen;
+use cursive::event::{Event, Key};
+use cursive::view::View;
+use cursive::{Cursive, CursiveRunner, Vec2};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// Size of the puppet terminal, wide enough for the whole layout
+const SCREEN_SIZE: Vec2 = Vec2::new(300, 60);
+
+/// TUI running on cursive's puppet backend, with the commands it sends kept
+/// for inspection
+struct Harness {
+    root: CursiveRunner<Cursive>,
+    screens: crossbeam_channel::Receiver<ObservedScreen>,
+    commands: UnboundedReceiver<NamedCommand>,
+}
+
+impl Harness {
+    /// Create the TUI for containers with the given names
+    fn new(names: &[&str]) -> Self {
+        let backend = Backend::init(Some(SCREEN_SIZE));
+        let screens = backend.stream();
+        let mut root = Cursive::new().into_runner(backend);
+        let (send, commands): (CommandSender, _) = mpsc::unbounded_channel();
+        root.set_user_data(send);
+        let containers = names.iter().map(|name| container(name)).collect();
+        Main::create(&mut root, &containers, 100);
+        Self {
+            root,
+            screens,
+            commands,
+        }
+    }
+
+    /// Handle a backend message about a container
+    fn update(&mut self, container: &str, update: Update) {
+        crate::handle_message(
+            &mut self.root,
+            NamedUpdate {
+                container: ContainerId::new(container),
+                inner: update,
+            },
+        );
+    }
+
+    /// Draw the TUI, getting the resulting screen
+    fn screen(&mut self) -> ObservedScreen {
+        self.root.refresh();
+        self.screens
+            .try_iter()
+            .last()
+            .expect("Refreshing should draw a screen")
+    }
+
+    /// Check if some text is on the screen
+    fn shows(&mut self, text: &str) -> bool {
+        !self.screen().find_occurences(text).is_empty()
+    }
+
+    /// Press the state button of a container
+    fn press_state_button(&mut self, container: &str) {
+        let result = Main::get_self(&mut self.root)
+            .get_container_list()
+            .get_container(&ContainerId::new(container))
+            .expect("Container should be listed")
+            .get_state_button()
+            .on_event(Event::Key(Key::Enter));
+        result.process(&mut self.root);
+    }
+
+    /// Type text into the focused view
+    fn type_text(&mut self, text: &str) {
+        for char in text.chars() {
+            self.root.on_event(Event::Char(char));
+        }
+    }
+
+    /// Get the commands sent to the backend so far
+    fn sent_commands(&mut self) -> Vec<NamedCommand> {
+        let mut commands = Vec::new();
+        while let Ok(command) = self.commands.try_recv() {
+            commands.push(command);
+        }
+        commands
+    }
+}
+
+fn container(name: &str) -> Container {
+    let id = ContainerId::new(name);
+    Container {
+        unit_name: format!("container@{name}.service"),
+        config_path: PathBuf::from(format!("/etc/nixos-containers/{name}.conf")),
+        state_root: PathBuf::from(format!("/var/lib/nixos-containers/{name}")),
+        config: Default::default(),
+        id,
+    }
+}
+
+fn entry(message: &str) -> Box<JournalEntry> {
+    Box::new(JournalEntry {
+        timestamp: 0,
+        cursor: message.to_string(),
+        priority: Some(Priority::Info),
+        pid: Some(1),
+        identifier: Some("test".to_string()),
+        message: message.to_string(),
+        fields: BTreeMap::new(),
+    })
+}
+
+#[test]
+fn lists_containers_in_unknown_state() {
+    let mut tui = Harness::new(&["alpha", "beta"]);
+    assert!(tui.shows("alpha"));
+    assert!(tui.shows("beta"));
+    assert!(tui.shows("Unknown"));
+    assert!(tui.shows("systemd: connected"));
+}
+
+#[test]
+fn state_updates_relabel_state_button() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("alpha", Update::State(ContainerState::Up));
+    assert!(tui.shows("<UP>"));
+    tui.update("alpha", Update::State(ContainerState::Down));
+    assert!(tui.shows("<DOWN>"));
+    assert!(!tui.shows("<UP>"));
+}
+
+#[test]
+fn state_button_sends_unit_operation() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("alpha", Update::State(ContainerState::Up));
+    tui.press_state_button("alpha");
+    tui.update("alpha", Update::State(ContainerState::Failed));
+    tui.press_state_button("alpha");
+    let commands = tui.sent_commands();
+    assert_eq!(commands.len(), 2);
+    assert!(
+        commands
+            .iter()
+            .all(|command| command.container.name() == "alpha")
+    );
+    assert_eq!(commands[0].inner, Command::Unit(UnitOperation::Stop));
+    assert_eq!(commands[1].inner, Command::Unit(UnitOperation::Start));
+}
+
+#[test]
+fn transitional_states_disable_state_button() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("alpha", Update::State(ContainerState::Starting));
+    assert!(tui.shows("STARTING"));
+    tui.press_state_button("alpha");
+    assert!(tui.sent_commands().is_empty());
+}
+
+#[test]
+fn shows_job_results() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("alpha", Update::JobFinished(JobResult::Failed));
+    assert!(tui.shows("Last job: failed"));
+}
+
+#[test]
+fn debug_log_shows_logs_and_errors() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("alpha", Update::Log("Monitoring".to_string()));
+    tui.update("alpha", Update::Error(anyhow!("Unit vanished")));
+    tui.update(
+        "alpha",
+        Update::Operation(UnitOperation::Restart, OperationOutcome::Rejected),
+    );
+    assert!(tui.shows("[LOG] alpha - Monitoring"));
+    assert!(tui.shows("[ERROR] alpha - Unit vanished"));
+    assert!(tui.shows("Ignored restart, already pending"));
+}
+
+#[test]
+fn shows_lost_connection() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update("systemd connection", Update::Connected(false));
+    assert!(tui.shows("systemd: DISCONNECTED"));
+    tui.update("systemd connection", Update::Connected(true));
+    assert!(tui.shows("systemd: connected"));
+}
+
+#[test]
+fn adds_and_removes_containers() {
+    let mut tui = Harness::new(&["alpha"]);
+    tui.update(
+        "gamma",
+        Update::ContainerAdded(Box::new(container("gamma"))),
+    );
+    tui.update("gamma", Update::State(ContainerState::Down));
+    assert!(tui.shows("<DOWN>"));
+    assert!(tui.shows("Container created"));
+    tui.update("gamma", Update::ContainerRemoved);
+    assert!(!tui.shows("<DOWN>"));
+    assert!(
+        Main::get_self(&mut tui.root)
+            .get_container_list()
+            .get_container(&ContainerId::new("gamma"))
+            .is_none()
+    );
+    // Late updates from the destroyed container are ignored
+    tui.update("gamma", Update::State(ContainerState::Up));
+    assert!(!tui.shows("<UP>"));
+}
+
+#[test]
+fn log_pane_shows_selected_container() {
+    let mut tui = Harness::new(&["alpha", "beta"]);
+    tui.update(
+        "alpha",
+        Update::ContainerLog(LogSource::Host, entry("alpha says hello")),
+    );
+    tui.update(
+        "beta",
+        Update::ContainerLog(LogSource::Host, entry("beta says hello")),
+    );
+    assert!(tui.shows("alpha says hello"));
+    assert!(!tui.shows("beta says hello"));
+    Main::get_self(&mut tui.root)
+        .get_container_log()
+        .show("beta");
+    assert!(tui.shows("beta says hello"));
+    assert!(!tui.shows("alpha says hello"));
+}
+
+#[test]
+fn destroy_needs_container_name_typed() {
+    let mut tui = Harness::new(&["alpha"]);
+    ContainerDestroy::open(&mut tui.root, &ContainerId::new("alpha"));
+    assert!(tui.shows("Destroy container - alpha"));
+    assert!(tui.shows("State root: /var/lib/nixos-containers/alpha"));
+    assert!(tui.shows("Config: /etc/nixos-containers/alpha.conf"));
+    // Confirm with the wrong name, then dismiss the complaint
+    tui.type_text("alph");
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Enter));
+    assert!(tui.shows("Type alpha to confirm destroying it"));
+    tui.root.on_event(Event::Key(Key::Enter));
+    assert!(tui.sent_commands().is_empty());
+    // Confirm with the right name
+    tui.root.on_event(Event::Key(Key::Up));
+    tui.type_text("a");
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Enter));
+    let commands = tui.sent_commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].container.name(), "alpha");
+    assert_eq!(commands[0].inner, Command::Destroy);
+    assert!(!tui.shows("Destroy container - alpha"));
+}
+
+#[test]
+fn rebuild_streams_output_and_offers_restart() {
+    let mut tui = Harness::new(&["alpha"]);
+    ContainerRebuild::open(&mut tui.root);
+    assert!(tui.shows("Rebuild container - alpha"));
+    // Rebuild from the current configuration
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Down));
+    tui.root.on_event(Event::Key(Key::Enter));
+    let commands = tui.sent_commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].inner, Command::Rebuild(None));
+    tui.update(
+        "alpha",
+        Update::BuildOutput("building the system".to_string()),
+    );
+    assert!(tui.shows("building the system"));
+    tui.update("alpha", Update::BuildSucceeded);
+    assert!(tui.shows("Build succeeded"));
+    assert!(tui.shows("<Restart>"));
+}
+
+#[test]
+fn log_dialogs_survive_container_removal() {
+    // Each dialog is opened for alpha, which is destroyed before it is used
+    let dialogs: [(char, &[Event]); 5] = [
+        ('f', &[Event::Char('X'), Event::Char('=')]),
+        ('p', &[]),
+        ('g', &[Event::Key(Key::Down)]),
+        ('/', &[Event::Char('x')]),
+        (
+            'h',
+            &[
+                Event::Key(Key::Down),
+                Event::Key(Key::Down),
+                Event::Key(Key::Down),
+            ],
+        ),
+    ];
+    for (key, events) in dialogs {
+        let mut tui = Harness::new(&["alpha", "beta"]);
+        tui.root.on_event(Event::Char(key));
+        tui.update("alpha", Update::ContainerRemoved);
+        for event in events {
+            tui.root.on_event(event.clone());
+        }
+        tui.root.on_event(Event::Key(Key::Enter));
+        assert!(tui.shows("Logs - beta"));
+    }
+}
+
+#[test]
+fn timestamps_round_trip() {
+    for usec in [
+        1_000_000,
+        951_782_400_000_000,
+        1_709_208_000_000_000,
+        4_107_542_399_000_000,
+    ] {
+        assert_eq!(parse_timestamp(&timestamp(usec)), Some(usec));
+    }
+}
+
+#[test]
+fn parses_leap_days() {
+    assert_eq!(
+        parse_timestamp("2024-02-29 12:00"),
+        Some(1_709_208_000_000_000)
+    );
+    assert_eq!(parse_timestamp("2000-02-29"), Some(951_782_400_000_000));
+    assert_eq!(parse_timestamp("2023-02-29"), None);
+    assert_eq!(parse_timestamp("1900-02-29"), None);
+}
+
+#[test]
+fn rejects_invalid_dates() {
+    for text in [
+        "2023-02-31",
+        "2023-04-31",
+        "2023-01-00",
+        "2023-01-32",
+        "2023-00-10",
+        "2023-13-10",
+        "1969-12-31",
+        "2023-01-10 24:00",
+        "2023-01-10 12:60",
+        "2023-01-10 12:00:60",
+        "2023-01-10-01",
+        "2023-01",
+        "yesterday",
+    ] {
+        assert_eq!(parse_timestamp(text), None, "{text}");
+    }
+}
+
+#[test]
+fn parses_timestamps_without_timezone() {
+    let usec = Some(1_700_000_000_000_000);
+    assert_eq!(parse_timestamp("2023-11-14 22:13:20 UTC"), usec);
+    assert_eq!(parse_timestamp("2023-11-14 22:13:20"), usec);
+    assert_eq!(parse_timestamp("  2023-11-14 22:13:20  "), usec);
+    assert_eq!(
+        parse_timestamp("2023-11-14 22:13"),
+        Some(1_699_999_980_000_000)
+    );
+    assert_eq!(parse_timestamp("2023-11-14"), Some(1_699_920_000_000_000));
+}
+
+#[test]
+fn log_line_cap_falls_back_to_default() {
+    assert_eq!(log_line_cap(None), Ok(DEFAULT_LOG_LINES));
+    assert_eq!(log_line_cap(Some(" 500 ")), Ok(500));
+    for lines in ["0", "-5", "lots", ""] {
+        let warning = log_line_cap(Some(lines)).expect_err(lines);
+        assert!(
+            warning.contains(&format!("keeping {DEFAULT_LOG_LINES}")),
+            "{warning}"
+        );
+    }
+}
+
+#[test]
+fn log_view_drops_oldest_lines_over_cap() {
+    let mut log = LogView::new(3);
+    for line in ["one", "two", "three", "four", "five"] {
+        log.push(line.to_string());
+    }
+    assert_eq!(log.items().collect::<Vec<_>>(), ["three", "four", "five"]);
+}
+
+/// Order log lines by the number they start with
+fn line_number(line: &str) -> u32 {
+    line.split(' ')
+        .next()
+        .and_then(|number| number.parse().ok())
+        .expect("Line should start with a number")
+}
+
+#[test]
+fn log_view_inserts_out_of_order_lines_by_key() {
+    let mut log = LogView::new(10);
+    for line in ["2", "5", "1", "4", "3", "0"] {
+        log.insert_by_key(line.to_string(), |line| line_number(line));
+    }
+    assert_eq!(
+        log.items().collect::<Vec<_>>(),
+        ["0", "1", "2", "3", "4", "5"]
+    );
+}
+
+#[test]
+fn log_view_inserts_equal_keys_in_arrival_order() {
+    let mut log = LogView::new(10);
+    for line in ["1 a", "3 a", "2 a", "1 b", "2 b", "3 b"] {
+        log.insert_by_key(line.to_string(), |line| line_number(line));
+    }
+    assert_eq!(
+        log.items().collect::<Vec<_>>(),
+        ["1 a", "1 b", "2 a", "2 b", "3 a", "3 b"]
+    );
+}
+
+#[test]
+fn log_view_search_wraps_around() {
+    let mut log = LogView::new(10);
+    for line in ["apple", "banana", "apricot", "cherry"] {
+        log.push(line.to_string());
+    }
+    log.set_search(Some(Regex::new("ap").unwrap()));
+    assert_eq!(log.select_match(true), Some(0));
+    assert_eq!(log.select_match(true), Some(2));
+    assert_eq!(log.select_match(true), Some(0));
+    assert_eq!(log.select_match(false), Some(2));
+    assert_eq!(log.select_match(false), Some(0));
+
+    // Only shown lines count, and the selection follows its line
+    log.set_filter(Some(Box::new(|line: &String| line != "banana")));
+    assert_eq!(log.select_match(true), Some(1));
+    log.set_search(Some(Regex::new("ap").unwrap()));
+    assert_eq!(log.select_match(false), Some(1));
+    log.prepend(vec!["fig".to_string()]);
+    assert_eq!(log.select_match(false), Some(1));
+    assert_eq!(log.select_match(false), Some(2));
+}
+
+#[test]
+fn log_view_search_without_matches_selects_nothing() {
+    let mut log = LogView::new(10);
+    log.push("apple".to_string());
+    assert_eq!(log.select_match(true), None);
+    log.set_search(Some(Regex::new("kiwi").unwrap()));
+    assert_eq!(log.select_match(true), None);
+    assert_eq!(log.select_match(false), None);
+}
@@ -0,0 +1,83 @@
+use super::Main;
+use super::utils::{list, optional, timestamp, yes_no};
+use crate::backend::messages::{ContainerId, UnitProperties};
+use cursive::Cursive;
+use cursive::view::{Nameable, ViewWrapper};
+use cursive::views::{Dialog, ScrollView, TextView};
+
+/// Dialog showing the systemd unit properties of a container
+pub struct UnitDetails {
+    inner: Dialog,
+}
+
+impl UnitDetails {
+    /// Open the unit details of a container on top of the TUI
+    pub fn open(root: &mut Cursive, container: &ContainerId) {
+        let mut details = Self {
+            inner: Dialog::around(ScrollView::new(TextView::new("Waiting for properties")))
+                .title(format!("Unit - {container}"))
+                .dismiss_button("Close"),
+        };
+        let properties = Main::get_self(root)
+            .get_container_list()
+            .get_container(container)
+            .and_then(|controls| controls.get_properties());
+        if let Some(properties) = properties {
+            details.update(properties);
+        }
+        root.add_layer(details.with_name(Self::view_name(container)));
+    }
+
+    /// Update the unit details of a container, if they are open
+    pub fn refresh(root: &mut Cursive, container: &ContainerId, properties: &UnitProperties) {
+        root.call_on_name(&Self::view_name(container), |details: &mut Self| {
+            details.update(properties)
+        });
+    }
+
+    fn update(&mut self, properties: &UnitProperties) {
+        let load_error = properties
+            .load_error
+            .as_ref()
+            .map(|(name, message)| format!("{name}: {message}"));
+        let lines = [
+            format!("Description: {}", properties.description),
+            format!("Load state: {}", properties.load_state),
+            format!(
+                "Active state: {} ({})",
+                properties.active_state, properties.sub_state
+            ),
+            format!(
+                "Active since: {}",
+                timestamp(properties.active_enter_timestamp)
+            ),
+            format!(
+                "Invocation ID: {}",
+                optional(Some(&properties.invocation_id).filter(|id| !id.is_empty()))
+            ),
+            format!("Unit file state: {}", properties.unit_file_state),
+            format!("Fragment path: {}", properties.fragment_path),
+            format!(
+                "Needs daemon reload: {}",
+                yes_no(properties.need_daemon_reload)
+            ),
+            format!("Load error: {}", optional(load_error)),
+            format!("Requires: {}", list(&properties.requires)),
+            format!("Wants: {}", list(&properties.wants)),
+        ];
+        self.inner
+            .get_content_mut()
+            .downcast_mut::<ScrollView<TextView>>()
+            .expect("Unit details content should be expected type")
+            .get_inner_mut()
+            .set_content(lines.join("\n"));
+    }
+
+    fn view_name(container: &ContainerId) -> String {
+        format!("unit-details-{container}")
+    }
+}
+
+impl ViewWrapper for UnitDetails {
+    cursive::wrap_impl!(self.inner: Dialog);
+}
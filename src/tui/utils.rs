@@ -1,16 +1,137 @@
 use cursive::view::View;
 use cursive::views::{ListChild, ListView};
+use std::fmt::Display;
 
 /// Get mutable access to a ListView item by label
 pub fn get_list_child<'a>(view: &'a mut ListView, label: &str) -> Option<&'a mut Box<dyn View>> {
-    view.children()
+    find_list_child(view, label).map(|index| match view.row_mut(index) {
+        ListChild::Row(_, view) => view,
+        _ => unreachable!(),
+    })
+}
+
+/// Get the index of a ListView item by label
+pub fn find_list_child(view: &ListView, label: &str) -> Option<usize> {
+    view.children().iter().position(|child| match child {
+        ListChild::Row(child_label, _) => child_label == label,
+        _ => false,
+    })
+}
+
+/// Format an optional value, showing a dash if it is unset
+pub fn optional(value: Option<impl Display>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+/// Format a list of values, showing a dash if it is empty
+pub fn list(values: &[impl Display]) -> String {
+    if values.is_empty() {
+        return "-".to_string();
+    }
+    values
         .iter()
-        .position(|child| match child {
-            ListChild::Row(child_label, _) => child_label == label,
-            _ => false,
-        })
-        .map(|index| match view.row_mut(index) {
-            ListChild::Row(_, view) => view,
-            _ => unreachable!(),
-        })
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Format a flag as yes or no
+pub fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/// Format a timestamp in microseconds since the Unix epoch as UTC, showing a
+/// dash if it is unset
+pub fn timestamp(usec: u64) -> String {
+    if usec == 0 {
+        return "-".to_string();
+    }
+    let secs = usec / 1_000_000;
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Convert days since the epoch to a civil date
+    // (see http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Parse a UTC time of the form `YYYY-MM-DD [HH:MM[:SS]]`, as formatted by
+/// [`timestamp`], into microseconds since the Unix epoch
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    let text = text.trim().trim_end_matches("UTC").trim_end();
+    let (date, time) = text.split_once(' ').unwrap_or((text, "00:00"));
+    let mut date = date.split('-').map(str::parse::<u64>);
+    let year = date.next()?.ok()?;
+    let month = date.next()?.ok()?;
+    let day = date.next()?.ok()?;
+    let mut time = time.trim().split(':').map(str::parse::<u64>);
+    let hours = time.next()?.ok()?;
+    let minutes = time.next()?.ok()?;
+    let seconds = time.next().transpose().ok()?.unwrap_or(0);
+    if date.next().is_some()
+        || time.next().is_some()
+        || year < 1970
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+        || hours > 23
+        || minutes > 59
+        || seconds > 59
+    {
+        return None;
+    }
+    // Convert the civil date to days since the epoch
+    // (see http://howardhinnant.github.io/date_algorithms.html#days_from_civil)
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Some((days * 86_400 + hours * 3_600 + minutes * 60 + seconds) * 1_000_000)
+}
+
+/// Number of days in a month of the Gregorian calendar
+fn days_in_month(year: u64, month: u64) -> u64 {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Format a byte count with a binary unit suffix, showing a dash if it is
+/// unavailable
+pub fn bytes(value: Option<u64>) -> String {
+    let Some(value) = value else {
+        return "-".to_string();
+    };
+    let mut value = value as f64;
+    for unit in ["B", "K", "M", "G", "T"] {
+        if value < 1024.0 {
+            return format!("{value:.1}{unit}");
+        }
+        value /= 1024.0;
+    }
+    format!("{value:.1}P")
 }